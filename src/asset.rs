@@ -1,8 +1,9 @@
 use std::ops::{Deref, DerefMut};
 
 use bevy::{
+    asset::{Asset, HandleId, LoadState},
     prelude::{
-        shape::{Cube, Plane},
+        shape::{Cube, Icosphere, Plane},
         *,
     },
     utils::hashbrown::HashMap,
@@ -10,17 +11,28 @@ use bevy::{
 
 use bevy_polyline::prelude::*;
 
+use crate::{
+    locale::{StringTable, LANGUAGES, SOURCE_LANGUAGE},
+    theme::Theme,
+};
+
 //fonts
 pub const FONT_SCHLUBER: &str = "Schluber.otf";
+///For letters that Schluber doesn't have, like cyrillic.
+pub const FONT_FALLBACK: &str = "DejaVuSans.ttf";
+
+//themes
+pub const THEME_DEFAULT: &str = "default.theme.ron";
 
 //images
 pub const IMAGE_UI: usize = 0;
-pub const CROSSHAIR: &str = "crosshair.png";
+pub const CROSSHAIR: &str = "Crosshair.png";
 
 //meshes
 pub const MESH_BUILT_IN: usize = 0;
 pub const CUBE: &str = "cube";
 pub const PLANE: &str = "plane";
+pub const SPHERE: &str = "sphere";
 pub const MESH_WEAPON: usize = 1;
 pub const GUN_TOWER_0_BASE: &str = "gun_tower0.glb#Mesh0/Primitive0";
 pub const GUN_TOWER_0_TOWER: &str = "gun_tower0.gltf#Mesh1/Primitive0";
@@ -30,7 +42,7 @@ pub const GUN_TOWER_0_GUN: &str = "gun_tower0.glb#Mesh2/Primitive0";
 pub const S_MAT_BUILT_IN: usize = 0;
 pub const WHITE: &str = "white";
 pub const WHITE_TRANS: &str = "white_trans";
-pub const SEA_GREEN: &str = "sea_green";
+pub const ROCK: &str = "rock";
 
 //polylines
 pub const UNIT_X: &str = "unit_x";
@@ -45,12 +57,16 @@ pub struct AssetManagingPlugin;
 impl Plugin for AssetManagingPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<Fonts>()
+            .init_resource::<Themes>()
+            .init_resource::<StringTables>()
             .init_resource::<Images>()
             .init_resource::<Meshes>()
             .init_resource::<StandardMaterials>()
             .init_resource::<Polylines>()
             .init_resource::<PolylineMaterials>()
-            .add_startup_system(assets_set_up);
+            .init_resource::<LoadProgress>()
+            .add_startup_system(assets_set_up)
+            .add_system_to_stage(CoreStage::PreUpdate, track_assets);
     }
 }
 
@@ -73,6 +89,13 @@ macro_rules! impl_handle_container {
                 &mut self.0
             }
         }
+
+        impl $name {
+            ///Every registered handle with its name.
+            pub fn handles(&self) -> impl Iterator<Item = (&'static str, &Handle<$handle>)> {
+                self.0.iter().map(|(name, handle)| (*name, handle))
+            }
+        }
     };
     ($(#[$meta:meta])* $name:ident, $handle:ident, $len:literal) => {
         $(#[$meta])*
@@ -92,6 +115,16 @@ macro_rules! impl_handle_container {
                 &mut self.0
             }
         }
+
+        impl $name {
+            ///Every registered handle with its name.
+            pub fn handles(&self) -> impl Iterator<Item = (&'static str, &Handle<$handle>)> {
+                self.0
+                    .iter()
+                    .flatten()
+                    .map(|(name, handle)| (*name, handle))
+            }
+        }
     };
 }
 
@@ -101,6 +134,18 @@ impl_handle_container!(
     Font
 );
 
+impl_handle_container!(
+    ///Theme handle access by str.
+    Themes,
+    Theme
+);
+
+impl_handle_container!(
+    ///String table handle access by language code.
+    StringTables,
+    StringTable
+);
+
 impl_handle_container!(
     ///Image handle access by str. Should index name be sank to whether type or path?
     Images,
@@ -139,6 +184,8 @@ impl_handle_container!(
 pub fn assets_set_up(
     asset_server: Res<AssetServer>,
     mut fonts: ResMut<Fonts>,
+    mut themes: ResMut<Themes>,
+    mut string_tables: ResMut<StringTables>,
     mut textures: ResMut<Images>,
     mut mesh_assets: ResMut<Assets<Mesh>>,
     mut meshes: ResMut<Meshes>,
@@ -156,6 +203,25 @@ pub fn assets_set_up(
         FONT_SCHLUBER,
         asset_server.load(fonts_dir.join(FONT_SCHLUBER)),
     );
+    fonts.insert(
+        FONT_FALLBACK,
+        asset_server.load(fonts_dir.join(FONT_FALLBACK)),
+    );
+    //themes
+    themes.insert(
+        THEME_DEFAULT,
+        asset_server.load(Path::new("ui").join(THEME_DEFAULT)),
+    );
+    //string tables
+    let lang_dir = Path::new("lang");
+    for (code, _) in LANGUAGES {
+        if code != SOURCE_LANGUAGE {
+            string_tables.insert(
+                code,
+                asset_server.load(lang_dir.join(format!("{code}.lang.ron"))),
+            );
+        }
+    }
     //textures
     let textures_dir = Path::new("textures");
     {
@@ -169,6 +235,16 @@ pub fn assets_set_up(
         //builtin
         meshes[MESH_BUILT_IN].insert(CUBE, mesh_assets.add(Cube::new(1.).into()));
         meshes[MESH_BUILT_IN].insert(PLANE, mesh_assets.add(Plane { size: 1. }.into()));
+        meshes[MESH_BUILT_IN].insert(
+            SPHERE,
+            mesh_assets.add(
+                Icosphere {
+                    radius: 1.,
+                    subdivisions: 1,
+                }
+                .into(),
+            ),
+        );
         //weapon
         let _weapon_dir = models_dir.join("weapon");
         meshes[MESH_WEAPON].insert(
@@ -194,8 +270,12 @@ pub fn assets_set_up(
             standard_material_assets.add((*Color::WHITE.set_a(0.4)).into()),
         );
         standard_materials[S_MAT_BUILT_IN].insert(
-            SEA_GREEN,
-            standard_material_assets.add(Color::SEA_GREEN.into()),
+            ROCK,
+            standard_material_assets.add(StandardMaterial {
+                base_color: Color::GRAY,
+                perceptual_roughness: 1.,
+                ..default()
+            }),
         );
     }
     //polylines
@@ -231,3 +311,94 @@ pub fn assets_set_up(
         }),
     );
 }
+
+///How many registered assets are ready.
+#[derive(Resource, Default)]
+pub struct LoadProgress {
+    pub loaded: usize,
+    pub total: usize,
+    ///Names of assets that failed to load, with their handles to load them again.
+    pub failed: Vec<(&'static str, HandleId)>,
+}
+
+impl LoadProgress {
+    pub fn is_done(&self) -> bool {
+        self.loaded == self.total && self.failed.is_empty()
+    }
+
+    ///Ratio of loaded assets in 0..=1.
+    pub fn ratio(&self) -> f32 {
+        match self.total {
+            0 => 1.,
+            total => self.loaded as f32 / total as f32,
+        }
+    }
+
+    ///Loads every failed asset again. They count as failed until their load ends.
+    pub fn retry(&self, asset_server: &AssetServer) {
+        for (_, id) in &self.failed {
+            if let Some(path) = asset_server.get_handle_path(*id) {
+                asset_server.reload_asset(path);
+            }
+        }
+    }
+
+    fn count<'a, T: Asset>(
+        &mut self,
+        handles: impl Iterator<Item = (&'static str, &'a Handle<T>)>,
+        assets: &Assets<T>,
+        asset_server: &AssetServer,
+    ) {
+        for (name, handle) in handles {
+            self.total += 1;
+            if assets.contains(handle) {
+                self.loaded += 1;
+            } else if asset_server.get_load_state(handle) == LoadState::Failed {
+                self.failed.push((name, handle.id()));
+            }
+        }
+    }
+}
+
+///Counts every handle registered in containers.
+pub fn track_assets(
+    mut progress: ResMut<LoadProgress>,
+    asset_server: Res<AssetServer>,
+    fonts: Res<Fonts>,
+    font_assets: Res<Assets<Font>>,
+    //Grouped to stay within system param limit.
+    (themes, theme_assets): (Res<Themes>, Res<Assets<Theme>>),
+    (string_tables, string_table_assets): (Res<StringTables>, Res<Assets<StringTable>>),
+    images: Res<Images>,
+    image_assets: Res<Assets<Image>>,
+    meshes: Res<Meshes>,
+    mesh_assets: Res<Assets<Mesh>>,
+    standard_materials: Res<StandardMaterials>,
+    standard_material_assets: Res<Assets<StandardMaterial>>,
+    polylines: Res<Polylines>,
+    polyline_assets: Res<Assets<Polyline>>,
+    polyline_materials: Res<PolylineMaterials>,
+    polyline_material_assets: Res<Assets<PolylineMaterial>>,
+) {
+    if progress.is_done() && progress.total > 0 {
+        return;
+    }
+    let mut next = LoadProgress::default();
+    next.count(fonts.handles(), &font_assets, &asset_server);
+    next.count(themes.handles(), &theme_assets, &asset_server);
+    next.count(string_tables.handles(), &string_table_assets, &asset_server);
+    next.count(images.handles(), &image_assets, &asset_server);
+    next.count(meshes.handles(), &mesh_assets, &asset_server);
+    next.count(
+        standard_materials.handles(),
+        &standard_material_assets,
+        &asset_server,
+    );
+    next.count(polylines.handles(), &polyline_assets, &asset_server);
+    next.count(
+        polyline_materials.handles(),
+        &polyline_material_assets,
+        &asset_server,
+    );
+    *progress = next;
+}
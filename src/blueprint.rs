@@ -0,0 +1,65 @@
+use std::{fs, io, path::Path};
+
+use bevy::prelude::*;
+
+///Towers to place when game starts.
+/// - Text file with a tower per line: `x y z` cell and optional quarter turns around y axis.
+/// - Empty lines and lines starting with `#` are skipped.
+#[derive(Resource, Debug, Default)]
+pub struct Blueprint {
+    pub towers: Vec<(IVec3, i32)>,
+}
+
+impl Blueprint {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        fs::read_to_string(path)?.parse()
+    }
+}
+
+impl std::str::FromStr for Blueprint {
+    type Err = io::Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut towers = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: expected `x y z [turns]`, got `{line}`", index + 1),
+                )
+            };
+            let numbers = line
+                .split_whitespace()
+                .map(|number| number.parse::<i32>().map_err(|_| invalid()))
+                .collect::<Result<Vec<_>, _>>()?;
+            match numbers[..] {
+                [x, y, z] => towers.push((IVec3::new(x, y, z), 0)),
+                [x, y, z, turns] => towers.push((IVec3::new(x, y, z), turns)),
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(Self { towers })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_towers() {
+        let blueprint: Blueprint = "# two towers\n\n 0 1 0 \n6 1 -6 1".parse().unwrap();
+        assert_eq!(
+            blueprint.towers,
+            [(IVec3::new(0, 1, 0), 0), (IVec3::new(6, 1, -6), 1)]
+        );
+        for invalid in ["0 1", "0 1 0 1 2", "0 one 0"] {
+            let err = invalid.parse::<Blueprint>().unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
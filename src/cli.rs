@@ -0,0 +1,207 @@
+use std::{fmt, path::PathBuf, str::FromStr};
+
+use crate::states::AppState;
+
+use bevy::log::Level;
+
+pub const USAGE: &str = "\
+Usage: game_made_with_rust [OPTIONS]
+
+Options:
+  --state <STATE>        State to go to: loading, main-menu or in-game. With window, it is
+                         entered after loading
+  --load <PATH>          Blueprint whose towers are placed when game starts
+  --seed <SEED>          Seed of map to generate, or flat
+  --window <WxH>         Window size, like 1280x720. Not with --headless
+  --headless             Run without window nor render on scripted input
+  --log-level <LEVEL>    trace, debug, info, warn or error
+  -h, --help             Print this";
+
+///Launch options, parsed before app is built.
+#[derive(Debug, Default)]
+pub struct Cli {
+    ///None is Main menu after loading, or In game when headless.
+    pub state: Option<AppState>,
+    pub load: Option<PathBuf>,
+    ///None keeps default map. Some(None) is flat map.
+    pub seed: Option<Option<u64>>,
+    pub window: Option<(f32, f32)>,
+    pub headless: bool,
+    pub log_level: Option<Level>,
+    pub help: bool,
+}
+
+///Why arguments couldn't be parsed.
+#[derive(Debug)]
+pub enum CliError {
+    Unknown(String),
+    MissingValue(&'static str),
+    InvalidValue(&'static str, String),
+    ///Both options are given, but they don't go together.
+    Conflict(&'static str, &'static str),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Unknown(arg) => write!(f, "unknown argument `{arg}`"),
+            CliError::MissingValue(option) => write!(f, "`{option}` needs a value"),
+            CliError::InvalidValue(option, value) => {
+                write!(f, "invalid value `{value}` for `{option}`")
+            }
+            CliError::Conflict(a, b) => write!(f, "`{a}` can't be used with `{b}`"),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+impl Cli {
+    ///Parses arguments of this process.
+    pub fn parse() -> Result<Self, CliError> {
+        Self::parse_from(std::env::args().skip(1))
+    }
+
+    ///Parses arguments without program name. Both `--option value` and `--option=value` work.
+    pub fn parse_from(args: impl IntoIterator<Item = String>) -> Result<Self, CliError> {
+        let mut cli = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (option, inline) = match arg.split_once('=') {
+                Some((option, value)) => (option.to_owned(), Some(value.to_owned())),
+                None => (arg, None),
+            };
+            //Every option that takes value takes it here.
+            let mut value = |name: &'static str| {
+                inline
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or(CliError::MissingValue(name))
+            };
+            match option.as_str() {
+                "--state" => {
+                    let state = value("--state")?;
+                    cli.state = Some(match state.as_str() {
+                        "loading" => AppState::Loading,
+                        "main-menu" => AppState::MainMenu(None),
+                        "in-game" => AppState::InGame(None),
+                        _ => return Err(CliError::InvalidValue("--state", state)),
+                    });
+                }
+                "--load" => cli.load = Some(value("--load")?.into()),
+                "--seed" => {
+                    let seed = value("--seed")?;
+                    cli.seed = Some(match seed.as_str() {
+                        "flat" => None,
+                        _ => Some(parse("--seed", seed)?),
+                    });
+                }
+                "--window" => {
+                    let size = value("--window")?;
+                    cli.window = Some(match size.split_once('x') {
+                        Some((width, height)) => (
+                            parse("--window", width.to_owned())?,
+                            parse("--window", height.to_owned())?,
+                        ),
+                        None => return Err(CliError::InvalidValue("--window", size)),
+                    });
+                }
+                "--headless" => cli.headless = true,
+                "--log-level" => cli.log_level = Some(parse("--log-level", value("--log-level")?)?),
+                "-h" | "--help" => cli.help = true,
+                _ => return Err(CliError::Unknown(option)),
+            }
+        }
+        if cli.headless && cli.window.is_some() {
+            return Err(CliError::Conflict("--window", "--headless"));
+        }
+        Ok(cli)
+    }
+
+    ///State to start at. Window always starts at Loading, so no state runs without its assets.
+    pub fn initial_state(&self) -> AppState {
+        match (self.state, self.headless) {
+            (Some(state), true) => state,
+            (None, true) => AppState::InGame(None),
+            (_, false) => AppState::Loading,
+        }
+    }
+
+    ///State that Loading goes to instead of Main menu.
+    pub fn after_loading(&self) -> Option<AppState> {
+        match self.state {
+            Some(AppState::Loading) => None,
+            _ if self.headless => None,
+            state => state,
+        }
+    }
+}
+
+fn parse<T: FromStr>(option: &'static str, value: String) -> Result<T, CliError> {
+    value
+        .parse()
+        .map_err(|_| CliError::InvalidValue(option, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, CliError> {
+        Cli::parse_from(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn options() {
+        let cli = parse(&[
+            "--state",
+            "in-game",
+            "--seed=42",
+            "--window",
+            "1280x720",
+            "--log-level",
+            "debug",
+            "--load",
+            "a.txt",
+        ])
+        .unwrap();
+        //Window loads assets first.
+        assert_eq!(cli.initial_state(), AppState::Loading);
+        assert_eq!(cli.after_loading(), Some(AppState::InGame(None)));
+        assert_eq!(cli.seed, Some(Some(42)));
+        assert_eq!(cli.window, Some((1280., 720.)));
+        assert_eq!(cli.log_level, Some(Level::DEBUG));
+        assert_eq!(cli.load, Some(PathBuf::from("a.txt")));
+        assert!(!cli.headless);
+    }
+
+    #[test]
+    fn defaults() {
+        assert_eq!(parse(&[]).unwrap().initial_state(), AppState::Loading);
+        assert_eq!(parse(&[]).unwrap().after_loading(), None);
+        let headless = parse(&["--headless", "--seed", "flat"]).unwrap();
+        assert_eq!(headless.initial_state(), AppState::InGame(None));
+        assert_eq!(headless.seed, Some(None));
+        //Headless starts right at given state.
+        let headless = parse(&["--headless", "--state", "main-menu"]).unwrap();
+        assert_eq!(headless.initial_state(), AppState::MainMenu(None));
+        assert_eq!(headless.after_loading(), None);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(parse(&["--fast"]), Err(CliError::Unknown(_))));
+        assert!(matches!(
+            parse(&["--state"]),
+            Err(CliError::MissingValue("--state"))
+        ));
+        assert!(matches!(
+            parse(&["--window", "1280"]),
+            Err(CliError::InvalidValue("--window", _))
+        ));
+        assert!(matches!(
+            parse(&["--window", "1280x720", "--headless"]),
+            Err(CliError::Conflict("--window", "--headless"))
+        ));
+    }
+}
@@ -0,0 +1,502 @@
+use bevy::{
+    input::{mouse::MouseWheel, InputSystem},
+    prelude::*,
+    utils::HashMap,
+};
+
+use crate::{settings::Settings, states::fade::ScreenFade};
+
+use serde::{Deserialize, Serialize};
+
+pub const CONTROLS_FILE: &str = "controls.ron";
+
+///What player wants to do, regardless of which input did it.
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
+pub enum InputAction {
+    MoveForward,
+    MoveBack,
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    Place,
+    Remove,
+    RotateGhost,
+    RotateGhostBack,
+    ///Select next item of build palette.
+    NextBuild,
+    ///Select previous item of build palette.
+    PreviousBuild,
+    ///Pause in game, and go back in menus.
+    Pause,
+    ///Focus previous button of menu.
+    MenuPrevious,
+    ///Focus next button of menu.
+    MenuNext,
+    ///Click focused button of menu.
+    MenuConfirm,
+    ///Go back in menus only.
+    MenuBack,
+}
+
+impl InputAction {
+    ///Every action in order they are listed.
+    pub const ALL: [InputAction; 17] = [
+        InputAction::MoveForward,
+        InputAction::MoveBack,
+        InputAction::MoveLeft,
+        InputAction::MoveRight,
+        InputAction::MoveUp,
+        InputAction::MoveDown,
+        InputAction::Place,
+        InputAction::Remove,
+        InputAction::RotateGhost,
+        InputAction::RotateGhostBack,
+        InputAction::NextBuild,
+        InputAction::PreviousBuild,
+        InputAction::Pause,
+        InputAction::MenuPrevious,
+        InputAction::MenuNext,
+        InputAction::MenuConfirm,
+        InputAction::MenuBack,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            InputAction::MoveForward => "Move forward",
+            InputAction::MoveBack => "Move back",
+            InputAction::MoveLeft => "Move left",
+            InputAction::MoveRight => "Move right",
+            InputAction::MoveUp => "Move up",
+            InputAction::MoveDown => "Move down",
+            InputAction::Place => "Place",
+            InputAction::Remove => "Remove",
+            InputAction::RotateGhost => "Rotate",
+            InputAction::RotateGhostBack => "Rotate back",
+            InputAction::NextBuild => "Next building",
+            InputAction::PreviousBuild => "Previous building",
+            InputAction::Pause => "Pause",
+            InputAction::MenuPrevious => "Menu previous",
+            InputAction::MenuNext => "Menu next",
+            InputAction::MenuConfirm => "Menu confirm",
+            InputAction::MenuBack => "Menu back",
+        }
+    }
+
+    ///Menu actions only act in menus and others only in game, except Pause that does both.
+    ///Actions that can act at once shouldn't share binding.
+    pub fn overlaps(self, other: InputAction) -> bool {
+        let is_menu = |action| {
+            matches!(
+                action,
+                InputAction::MenuPrevious
+                    | InputAction::MenuNext
+                    | InputAction::MenuConfirm
+                    | InputAction::MenuBack
+            )
+        };
+        self == InputAction::Pause || other == InputAction::Pause || is_menu(self) == is_menu(other)
+    }
+}
+
+///Physical input that triggers action.
+/// - Wheel is pressed only on the frame it is scrolled.
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    WheelUp,
+    WheelDown,
+    ///Button of any connected gamepad.
+    Gamepad(GamepadButtonType),
+}
+
+impl Binding {
+    pub fn is_gamepad(&self) -> bool {
+        matches!(self, Binding::Gamepad(_))
+    }
+}
+
+impl std::fmt::Display for Binding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Binding::Key(key) => write!(f, "{key:?}"),
+            Binding::Mouse(MouseButton::Other(button)) => write!(f, "Mouse {button}"),
+            Binding::Mouse(button) => write!(f, "Mouse {button:?}"),
+            Binding::WheelUp => write!(f, "Wheel up"),
+            Binding::WheelDown => write!(f, "Wheel down"),
+            Binding::Gamepad(GamepadButtonType::Other(button)) => write!(f, "Pad {button}"),
+            Binding::Gamepad(button) => write!(f, "Pad {button:?}"),
+        }
+    }
+}
+
+///Place of binding in action: n-th one of keyboard and mouse, or n-th one of gamepad.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct Slot {
+    pub gamepad: bool,
+    pub index: usize,
+}
+
+impl Slot {
+    ///Slots each action shows, primary and secondary of each device.
+    pub const ALL: [Slot; 4] = [
+        Slot::new(false, 0),
+        Slot::new(false, 1),
+        Slot::new(true, 0),
+        Slot::new(true, 1),
+    ];
+
+    pub const fn new(gamepad: bool, index: usize) -> Self {
+        Self { gamepad, index }
+    }
+
+    ///Whether binding is of device of slot.
+    pub fn fits(&self, binding: &Binding) -> bool {
+        binding.is_gamepad() == self.gamepad
+    }
+}
+
+///Inputs that trigger each action. Systems read `Input<InputAction>` that is made from it.
+#[derive(Resource, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct InputMap(HashMap<InputAction, Vec<Binding>>);
+
+impl Default for InputMap {
+    fn default() -> Self {
+        use {Binding::*, InputAction::*};
+        Self(HashMap::from_iter([
+            (MoveForward, vec![Key(KeyCode::W), Key(KeyCode::Up)]),
+            (MoveBack, vec![Key(KeyCode::S), Key(KeyCode::Down)]),
+            (MoveLeft, vec![Key(KeyCode::A), Key(KeyCode::Left)]),
+            (MoveRight, vec![Key(KeyCode::D), Key(KeyCode::Right)]),
+            (
+                MoveUp,
+                vec![Key(KeyCode::Space), Gamepad(GamepadButtonType::North)],
+            ),
+            (
+                MoveDown,
+                vec![Key(KeyCode::LShift), Gamepad(GamepadButtonType::West)],
+            ),
+            (
+                Place,
+                vec![
+                    Mouse(MouseButton::Left),
+                    Gamepad(GamepadButtonType::RightTrigger2),
+                ],
+            ),
+            (
+                Remove,
+                vec![
+                    Mouse(MouseButton::Right),
+                    Gamepad(GamepadButtonType::LeftTrigger2),
+                ],
+            ),
+            (
+                RotateGhost,
+                vec![Key(KeyCode::E), Gamepad(GamepadButtonType::RightTrigger)],
+            ),
+            (
+                RotateGhostBack,
+                vec![Key(KeyCode::Q), Gamepad(GamepadButtonType::LeftTrigger)],
+            ),
+            (
+                NextBuild,
+                vec![WheelDown, Gamepad(GamepadButtonType::DPadRight)],
+            ),
+            (
+                PreviousBuild,
+                vec![WheelUp, Gamepad(GamepadButtonType::DPadLeft)],
+            ),
+            (
+                Pause,
+                vec![Key(KeyCode::Escape), Gamepad(GamepadButtonType::Start)],
+            ),
+            (
+                MenuPrevious,
+                vec![
+                    Key(KeyCode::Up),
+                    Key(KeyCode::Left),
+                    Gamepad(GamepadButtonType::DPadUp),
+                    Gamepad(GamepadButtonType::DPadLeft),
+                ],
+            ),
+            (
+                MenuNext,
+                vec![
+                    Key(KeyCode::Down),
+                    Key(KeyCode::Right),
+                    Gamepad(GamepadButtonType::DPadDown),
+                    Gamepad(GamepadButtonType::DPadRight),
+                ],
+            ),
+            (
+                MenuConfirm,
+                vec![Key(KeyCode::Return), Gamepad(GamepadButtonType::South)],
+            ),
+            (
+                MenuBack,
+                vec![Key(KeyCode::Back), Gamepad(GamepadButtonType::East)],
+            ),
+        ]))
+    }
+}
+
+impl InputMap {
+    pub fn bindings(&self, action: InputAction) -> &[Binding] {
+        self.0.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    ///Binding in slot of action, if there is.
+    pub fn binding(&self, action: InputAction, slot: Slot) -> Option<Binding> {
+        self.position(action, slot)
+            .map(|position| self.bindings(action)[position])
+    }
+
+    ///Index in bindings of action that slot points.
+    fn position(&self, action: InputAction, slot: Slot) -> Option<usize> {
+        self.bindings(action)
+            .iter()
+            .enumerate()
+            .filter(|(_, binding)| slot.fits(binding))
+            .nth(slot.index)
+            .map(|(position, _)| position)
+    }
+
+    ///Puts binding, which must be of device of slot, in slot. Empty slot gets it after others.
+    /// - Other bindings of action are kept, and same binding elsewhere in action is dropped.
+    /// - Other actions keep it too, so none is left unbound. See conflicts.
+    pub fn rebind(&mut self, action: InputAction, slot: Slot, binding: Binding) {
+        debug_assert!(slot.fits(&binding), "{binding} doesn't fit {slot:?}");
+        let position = self.position(action, slot);
+        let bindings = self.0.entry(action).or_default();
+        match position {
+            Some(position) => bindings[position] = binding,
+            None => bindings.push(binding),
+        }
+        let mut seen = false;
+        bindings.retain(|other| *other != binding || !std::mem::replace(&mut seen, true));
+    }
+
+    ///Empties slot of action. Slots after it move up.
+    pub fn unbind(&mut self, action: InputAction, slot: Slot) {
+        if let Some(position) = self.position(action, slot) {
+            self.0.entry(action).or_default().remove(position);
+        }
+    }
+
+    ///Bindings shared by actions that can act at once, with those actions in order of ALL.
+    pub fn conflicts(&self) -> Vec<(Binding, InputAction, InputAction)> {
+        let mut conflicts = Vec::new();
+        for (i, &a) in InputAction::ALL.iter().enumerate() {
+            for &b in InputAction::ALL[i + 1..].iter().filter(|b| a.overlaps(**b)) {
+                for binding in self.bindings(a) {
+                    if self.bindings(b).contains(binding) {
+                        conflicts.push((*binding, a, b));
+                    }
+                }
+            }
+        }
+        conflicts
+    }
+
+    ///Actions that file doesn't have, like newly added ones, get default bindings.
+    pub fn fill_defaults(mut self) -> Self {
+        for (action, bindings) in Self::default().0 {
+            self.0.entry(action).or_insert(bindings);
+        }
+        self
+    }
+}
+
+///Stick positions of every connected gamepad added up, after dead zone of settings.
+/// - Each is at most 1 long.
+#[derive(Resource, Default, Clone, Copy, Debug)]
+pub struct Sticks {
+    ///Left stick, which moves camera.
+    pub movement: Vec2,
+    ///Right stick, which turns camera.
+    pub look: Vec2,
+}
+
+///Label of system that updates actions. Systems reading actions in PreUpdate go after it.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, SystemLabel)]
+pub struct ActionSystem;
+
+///Batch setup of input actions.
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputMap>()
+            .init_resource::<Input<InputAction>>()
+            .init_resource::<Sticks>()
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                update_actions.label(ActionSystem).after(InputSystem),
+            )
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                update_sticks.label(ActionSystem).after(InputSystem),
+            );
+    }
+}
+
+///Presses and releases actions by whether any of their bindings is pressed.
+fn update_actions(
+    mut actions: ResMut<Input<InputAction>>,
+    map: Res<InputMap>,
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<MouseButton>>,
+    gamepads: Res<Gamepads>,
+    pad_buttons: Res<Input<GamepadButton>>,
+    mut wheel: EventReader<MouseWheel>,
+) {
+    let scroll = wheel.iter().map(|w| w.y).sum::<f32>();
+    actions.clear();
+    for action in InputAction::ALL {
+        let bindings = map.bindings(action);
+        let pressed = bindings.iter().any(|binding| match *binding {
+            Binding::Key(key) => keys.pressed(key),
+            Binding::Mouse(button) => buttons.pressed(button),
+            Binding::Gamepad(button) => gamepads
+                .iter()
+                .any(|gamepad| pad_buttons.pressed(GamepadButton::new(gamepad, button))),
+            Binding::WheelUp | Binding::WheelDown => false,
+        });
+        //Every scroll counts as new press, even on consecutive frames.
+        let scrolled = bindings.iter().any(|binding| match *binding {
+            Binding::WheelUp => scroll > 0.,
+            Binding::WheelDown => scroll < 0.,
+            _ => false,
+        });
+        if scrolled || !pressed {
+            actions.release(action);
+        }
+        if scrolled || pressed {
+            actions.press(action);
+        }
+    }
+}
+
+///Reads sticks of every gamepad. They rest while screen fades, like every other input.
+fn update_sticks(
+    mut sticks: ResMut<Sticks>,
+    gamepads: Res<Gamepads>,
+    axes: Res<Axis<GamepadAxis>>,
+    settings: Res<Settings>,
+    screen_fade: Res<ScreenFade>,
+) {
+    let stick = |x, y| {
+        if screen_fade.is_running() {
+            return Vec2::ZERO;
+        }
+        gamepads
+            .iter()
+            .map(|gamepad| {
+                let axis = |axis_type| axes.get(GamepadAxis::new(gamepad, axis_type)).unwrap_or(0.);
+                dead_zone(Vec2::new(axis(x), axis(y)), settings.stick_dead_zone)
+            })
+            .sum::<Vec2>()
+            .clamp_length_max(1.)
+    };
+    let movement = stick(GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY);
+    let look = stick(GamepadAxisType::RightStickX, GamepadAxisType::RightStickY);
+    //Keep change detection quiet while sticks rest.
+    if sticks.movement != movement || sticks.look != look {
+        *sticks = Sticks { movement, look };
+    }
+}
+
+///Zero inside dead zone, and scaled so that it still goes smoothly from 0 to 1 outside.
+fn dead_zone(stick: Vec2, dead_zone: f32) -> Vec2 {
+    let length = stick.length();
+    if length <= dead_zone {
+        Vec2::ZERO
+    } else {
+        stick * ((length.min(1.) - dead_zone) / (1. - dead_zone) / length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_map_keeps_new_actions() {
+        let mut map = InputMap::default();
+        map.rebind(InputAction::Place, KEY, Binding::Key(KeyCode::E));
+        let text = ron::to_string(&map).unwrap();
+        assert_eq!(ron::from_str::<InputMap>(&text).unwrap(), map);
+
+        //File from before an action existed.
+        map.0.remove(&InputAction::Pause);
+        let map = map.fill_defaults();
+        assert_eq!(
+            map.bindings(InputAction::Pause),
+            InputMap::default().bindings(InputAction::Pause)
+        );
+        assert_eq!(
+            map.bindings(InputAction::Place),
+            [
+                Binding::Key(KeyCode::E),
+                Binding::Gamepad(GamepadButtonType::RightTrigger2)
+            ]
+        );
+    }
+
+    const KEY: Slot = Slot::new(false, 0);
+    const KEY_2: Slot = Slot::new(false, 1);
+    const PAD: Slot = Slot::new(true, 0);
+
+    #[test]
+    fn rebind_slots() {
+        use {Binding::*, InputAction::*};
+        let mut map = InputMap::default();
+        //Only slot of its device is replaced.
+        map.rebind(MoveForward, KEY, Key(KeyCode::I));
+        assert_eq!(
+            map.bindings(MoveForward),
+            [Key(KeyCode::I), Key(KeyCode::Up)]
+        );
+        map.rebind(MoveForward, PAD, Gamepad(GamepadButtonType::DPadUp));
+        assert_eq!(
+            map.binding(MoveForward, PAD),
+            Some(Gamepad(GamepadButtonType::DPadUp))
+        );
+        assert_eq!(map.binding(MoveForward, KEY_2), Some(Key(KeyCode::Up)));
+        //Same binding isn't kept twice.
+        map.rebind(MoveForward, KEY_2, Key(KeyCode::I));
+        assert_eq!(map.binding(MoveForward, KEY), Some(Key(KeyCode::I)));
+        assert_eq!(map.binding(MoveForward, KEY_2), None);
+        map.unbind(MoveForward, KEY);
+        assert_eq!(
+            map.bindings(MoveForward),
+            [Gamepad(GamepadButtonType::DPadUp)]
+        );
+    }
+
+    #[test]
+    fn conflicts_of_overlapping_actions() {
+        use {Binding::*, InputAction::*};
+        //Arrows both move and navigate menus, which never act at once.
+        let mut map = InputMap::default();
+        assert_eq!(map.conflicts(), []);
+        map.rebind(Place, KEY, Key(KeyCode::W));
+        map.rebind(MenuConfirm, KEY, Key(KeyCode::Escape));
+        assert_eq!(
+            map.conflicts(),
+            [
+                (Key(KeyCode::W), MoveForward, Place),
+                (Key(KeyCode::Escape), Pause, MenuConfirm)
+            ]
+        );
+    }
+
+    #[test]
+    fn stick_dead_zone() {
+        assert_eq!(dead_zone(Vec2::new(0.1, 0.1), 0.2), Vec2::ZERO);
+        assert!((dead_zone(Vec2::new(0., 0.6), 0.2).y - 0.5).abs() < 1e-6);
+        assert!((dead_zone(Vec2::new(1., 1.), 0.2).length() - 1.).abs() < 1e-6);
+        assert_eq!(dead_zone(Vec2::X, 0.), Vec2::X);
+    }
+}
@@ -1,28 +1,39 @@
-use macros::impl_with_tuples;
+use bevy::{ecs::system::BoxedSystem, prelude::*};
 
-use bevy::prelude::Component;
-
-///Container component for function.
+///Container component for system that runs when triggered, like on click of button.
+/// - System can take any params, and keeps its locals between runs.
 #[derive(Component)]
-pub struct Action<F>(F);
+pub struct Action {
+    ///Taken out while running, since system needs the whole world.
+    system: Option<BoxedSystem>,
+    initialized: bool,
+}
 
-impl<F> Action<F> {
-    pub fn new(f: F) -> Self {
-        Action(f)
+impl Action {
+    pub fn new<Params>(system: impl IntoSystem<(), (), Params>) -> Self {
+        Action {
+            system: Some(Box::new(IntoSystem::into_system(system))),
+            initialized: false,
+        }
     }
-}
 
-///impl Action for various function types.
-macro_rules! impl_action {
-    ($($param: ident),*) => {
-        impl<$($param),*> Action<fn($($param),*)>{
-        #[inline]
-        #[allow(dead_code)]
-            pub fn run(&self$(, $param: $param)*){
-                self.0($($param),*)
-            }
+    ///Runs action of entity and applies its commands right away. Action may despawn entity.
+    pub fn run(world: &mut World, entity: Entity) {
+        let Some(mut action) = world.get_mut::<Action>(entity) else {
+            return;
+        };
+        let initialized = action.initialized;
+        let Some(mut system) = action.system.take() else {
+            return;
+        };
+        if !initialized {
+            system.initialize(world);
         }
-    };
+        system.run((), world);
+        system.apply_buffers(world);
+        if let Some(mut action) = world.get_mut::<Action>(entity) {
+            action.system = Some(system);
+            action.initialized = true;
+        }
+    }
 }
-
-impl_with_tuples!(impl_action, 0, 16, P);
@@ -0,0 +1,1018 @@
+use std::{collections::VecDeque, time::Duration};
+
+use bevy::{
+    app::{AppExit, ScheduleRunnerSettings},
+    input::{
+        gamepad::{GamepadEventRaw, GamepadEventType, GamepadInfo},
+        keyboard::KeyboardInput,
+        mouse::{MouseButtonInput, MouseMotion, MouseScrollUnit, MouseWheel},
+        ButtonState, InputPlugin, InputSystem,
+    },
+    prelude::*,
+    time::TimeUpdateStrategy,
+    utils::Instant,
+};
+
+use bevy_polyline::prelude::*;
+
+///Length of every frame in headless mode, so a script runs the same way every time.
+pub const FRAME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+///Everything game logic needs from DefaultPlugins, without window or render.
+/// - Time advances by FRAME on every update rather than by clock.
+/// - Input comes from InputScript.
+pub struct HeadlessPlugin;
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ScheduleRunnerSettings::run_loop(FRAME))
+            .add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualInstant(Instant::now()))
+            .add_plugin(TransformPlugin)
+            .add_plugin(HierarchyPlugin)
+            .add_plugin(InputPlugin)
+            .add_plugin(WindowPlugin {
+                add_primary_window: false,
+                exit_on_all_closed: false,
+                close_when_requested: false,
+                ..default()
+            })
+            .add_plugin(AssetPlugin::default())
+            //Asset types that render plugins would register.
+            .add_asset::<Font>()
+            .add_asset::<Image>()
+            .add_asset::<Mesh>()
+            .add_asset::<StandardMaterial>()
+            .add_asset::<Polyline>()
+            .add_asset::<PolylineMaterial>()
+            .init_resource::<InputScript>()
+            .add_system_to_stage(CoreStage::First, step_time)
+            .add_system_to_stage(CoreStage::PreUpdate, feed_script.before(InputSystem));
+    }
+}
+
+///Single input that script feeds as if it came from device.
+#[derive(Clone, Copy, Debug)]
+pub enum ScriptStep {
+    Press(KeyCode),
+    Release(KeyCode),
+    PressMouse(MouseButton),
+    ReleaseMouse(MouseButton),
+    ///Mouse motion in pixels.
+    Look(Vec2),
+    ///Mouse wheel in lines.
+    Scroll(f32),
+    PressPad(GamepadButtonType),
+    ReleasePad(GamepadButtonType),
+    ///Axis of stick, from -1 to 1.
+    Tilt(GamepadAxisType, f32),
+    Exit,
+}
+
+///Steps to feed on given frames, counted from first update.
+#[derive(Resource, Default)]
+pub struct InputScript {
+    frame: u32,
+    ///Frame of last step, so next one can be put after it.
+    last: u32,
+    steps: VecDeque<(u32, ScriptStep)>,
+    ///Gamepad steps come from gamepad 0, which connects on first of them.
+    pad_connected: bool,
+}
+
+impl InputScript {
+    ///Adds step that is fed given frames after previous one.
+    pub fn then(mut self, wait: u32, step: ScriptStep) -> Self {
+        self.push(wait, step);
+        self
+    }
+
+    ///Adds step that is fed given frames after previous one.
+    pub fn push(&mut self, wait: u32, step: ScriptStep) {
+        self.last = self.last.max(self.frame) + wait;
+        self.steps.push_back((self.last, step));
+    }
+
+    ///Places a tower, pauses and resumes, removes it, looks around with mouse and gamepad and
+    ///exits.
+    pub fn smoke() -> Self {
+        Self::default()
+            .then(10, ScriptStep::PressMouse(MouseButton::Left))
+            .then(1, ScriptStep::ReleaseMouse(MouseButton::Left))
+            .then(10, ScriptStep::Press(KeyCode::Escape))
+            .then(1, ScriptStep::Release(KeyCode::Escape))
+            .then(10, ScriptStep::Press(KeyCode::Escape))
+            .then(1, ScriptStep::Release(KeyCode::Escape))
+            .then(10, ScriptStep::PressMouse(MouseButton::Right))
+            .then(1, ScriptStep::ReleaseMouse(MouseButton::Right))
+            .then(10, ScriptStep::Look(Vec2::new(200., 0.)))
+            .then(1, ScriptStep::Scroll(1.))
+            .then(1, ScriptStep::Tilt(GamepadAxisType::RightStickX, 1.))
+            .then(10, ScriptStep::Tilt(GamepadAxisType::RightStickX, 0.))
+            .then(1, ScriptStep::PressPad(GamepadButtonType::RightTrigger))
+            .then(1, ScriptStep::ReleasePad(GamepadButtonType::RightTrigger))
+            .then(60, ScriptStep::Exit)
+    }
+}
+
+///Sets time of next update one frame later than this one.
+fn step_time(mut strategy: ResMut<TimeUpdateStrategy>) {
+    if let TimeUpdateStrategy::ManualInstant(instant) = strategy.as_mut() {
+        *instant += FRAME;
+    }
+}
+
+///Sends every step due this frame before input is processed.
+fn feed_script(
+    mut script: ResMut<InputScript>,
+    mut keys: EventWriter<KeyboardInput>,
+    mut buttons: EventWriter<MouseButtonInput>,
+    mut motion: EventWriter<MouseMotion>,
+    mut wheel: EventWriter<MouseWheel>,
+    mut pad: EventWriter<GamepadEventRaw>,
+    mut exit: EventWriter<AppExit>,
+) {
+    while let Some(&(frame, step)) = script.steps.front() {
+        if frame > script.frame {
+            break;
+        }
+        script.steps.pop_front();
+        debug!("Scripted {step:?} on frame {frame}");
+        let gamepad = Gamepad::new(0);
+        let is_pad = matches!(
+            step,
+            ScriptStep::PressPad(_) | ScriptStep::ReleasePad(_) | ScriptStep::Tilt(..)
+        );
+        if is_pad && !script.pad_connected {
+            script.pad_connected = true;
+            pad.send(GamepadEventRaw::new(
+                gamepad,
+                GamepadEventType::Connected(GamepadInfo {
+                    name: "Scripted".to_owned(),
+                }),
+            ));
+        }
+        match step {
+            ScriptStep::Press(key) => keys.send(KeyboardInput {
+                scan_code: 0,
+                key_code: Some(key),
+                state: ButtonState::Pressed,
+            }),
+            ScriptStep::Release(key) => keys.send(KeyboardInput {
+                scan_code: 0,
+                key_code: Some(key),
+                state: ButtonState::Released,
+            }),
+            ScriptStep::PressMouse(button) => buttons.send(MouseButtonInput {
+                button,
+                state: ButtonState::Pressed,
+            }),
+            ScriptStep::ReleaseMouse(button) => buttons.send(MouseButtonInput {
+                button,
+                state: ButtonState::Released,
+            }),
+            ScriptStep::Look(delta) => motion.send(MouseMotion { delta }),
+            ScriptStep::Scroll(y) => wheel.send(MouseWheel {
+                unit: MouseScrollUnit::Line,
+                x: 0.,
+                y,
+            }),
+            ScriptStep::PressPad(button) => pad.send(GamepadEventRaw::new(
+                gamepad,
+                GamepadEventType::ButtonChanged(button, 1.),
+            )),
+            ScriptStep::ReleasePad(button) => pad.send(GamepadEventRaw::new(
+                gamepad,
+                GamepadEventType::ButtonChanged(button, 0.),
+            )),
+            ScriptStep::Tilt(axis, value) => pad.send(GamepadEventRaw::new(
+                gamepad,
+                GamepadEventType::AxisChanged(axis, value),
+            )),
+            ScriptStep::Exit => exit.send(AppExit),
+        }
+    }
+    script.frame += 1;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        add_game_plugins,
+        asset::{LoadProgress, StringTables, Themes, THEME_DEFAULT},
+        blueprint::Blueprint,
+        controls::{Binding, InputAction, InputMap, Slot},
+        layout::Anchored,
+        physics::{
+            collider::{Collider, Shape},
+            occupancy::OccupancyGrid,
+            spatial::Spatial,
+        },
+        settings::Settings,
+        states::{
+            controls::ControlsButton,
+            fade::{Fade, ScreenFade},
+            in_game::*,
+            loading::AfterLoading,
+            palette::Palette,
+            settings::SettingsButton,
+            *,
+        },
+        terrain::{generate::MapSeed, Terrain},
+        theme::{Theme, ThemedText},
+        ui::{Disabled, Focus, CONTINUE_TEXT, NO_TEXT, PLAY_TEXT, RETRY_TEXT, WAVE_STARTED_TEXT},
+    };
+
+    use bevy::{
+        asset::Asset,
+        ecs::query::ReadOnlyWorldQuery,
+        window::{WindowCloseRequested, WindowId, WindowResized},
+    };
+
+    ///Headless app that tests drive frame by frame.
+    struct Harness(App);
+
+    impl Harness {
+        fn new(initial: AppState) -> Self {
+            let mut app = App::new();
+            app.add_plugin(HeadlessPlugin);
+            add_game_plugins(&mut app, initial);
+            //Flat map so placement is predictable.
+            app.insert_resource(MapSeed(None));
+            let mut harness = Self(app);
+            harness.update(2);
+            harness
+        }
+
+        fn update(&mut self, frames: u32) {
+            for _ in 0..frames {
+                self.0.update();
+            }
+        }
+
+        ///Feeds step on next frame and lets state follow up.
+        fn input(&mut self, step: ScriptStep) {
+            self.0.world.resource_mut::<InputScript>().push(0, step);
+            self.update(2);
+        }
+
+        fn tap(&mut self, key: KeyCode) {
+            self.input(ScriptStep::Press(key));
+            self.input(ScriptStep::Release(key));
+        }
+
+        fn click(&mut self, button: MouseButton) {
+            self.input(ScriptStep::PressMouse(button));
+            self.input(ScriptStep::ReleaseMouse(button));
+        }
+
+        fn tap_pad(&mut self, button: GamepadButtonType) {
+            self.input(ScriptStep::PressPad(button));
+            self.input(ScriptStep::ReleasePad(button));
+        }
+
+        fn camera(&mut self) -> Transform {
+            *self
+                .0
+                .world
+                .query_filtered::<&Transform, With<Camera3d>>()
+                .single(&self.0.world)
+        }
+
+        ///Lets asset load from file, so file doesn't override changes made after.
+        fn wait_for<A: Asset>(&mut self, handle: &Handle<A>) {
+            for _ in 0..2000 {
+                if self.0.world.resource::<Assets<A>>().contains(handle) {
+                    return;
+                }
+                std::thread::sleep(Duration::from_millis(1));
+                self.update(1);
+            }
+            panic!("asset didn't load");
+        }
+
+        ///Values of every text on screen.
+        fn texts(&mut self) -> Vec<String> {
+            self.0
+                .world
+                .query::<&Text>()
+                .iter(&self.0.world)
+                .map(|text| text.sections[0].value.clone())
+                .collect()
+        }
+
+        ///Replaces state without fade.
+        fn replace(&mut self, to: AppState) {
+            self.global().replace(to).unwrap();
+            self.update(1);
+        }
+
+        fn global(&mut self) -> Mut<'_, GlobalState> {
+            self.0.world.resource_mut::<GlobalState>()
+        }
+
+        fn state(&self) -> AppState {
+            self.0.world.resource::<GlobalState>().current()
+        }
+
+        fn count<F: ReadOnlyWorldQuery + 'static>(&mut self) -> usize {
+            self.0
+                .world
+                .query_filtered::<(), F>()
+                .iter(&self.0.world)
+                .count()
+        }
+    }
+
+    type Towers = (With<Collider>, Without<Obstacle>, Without<Debris>);
+
+    const KEY_SLOT: Slot = Slot::new(false, 0);
+
+    #[test]
+    fn enters_initial_state() {
+        let mut harness = Harness::new(AppState::InGame(None));
+        assert_eq!(harness.state(), AppState::InGame(None));
+        assert_eq!(harness.count::<With<Terrain>>(), 1);
+        assert_eq!(harness.count::<With<OccupancyGrid>>(), 1);
+        assert_eq!(harness.count::<Towers>(), 0);
+        //Flat ground is in spatial tree by its chunks.
+        let chunks = harness.count::<With<Ground>>();
+        assert!(chunks > 0);
+        let spatial = harness.0.world.query::<&Spatial>().single(&harness.0.world);
+        assert_eq!(spatial.static_len(), chunks);
+    }
+
+    ///Waits for assets in Loading, and presses Continue that shows up as some of them fail.
+    fn continue_loading(harness: &mut Harness) {
+        //Headless has no font, image or gltf loaders, so those fail.
+        for _ in 0..2000 {
+            let progress = harness.0.world.resource::<LoadProgress>();
+            if progress.total > 0 && progress.loaded + progress.failed.len() == progress.total {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+            harness.update(1);
+        }
+        assert!(!harness.0.world.resource::<LoadProgress>().failed.is_empty());
+        harness.update(2);
+        assert_eq!(harness.state(), AppState::Loading);
+        assert!(harness.texts().contains(&RETRY_TEXT.to_owned()));
+
+        let is_continue = |harness: &mut Harness| {
+            let focus = harness.0.world.resource::<Focus>().0;
+            let world = &harness.0.world;
+            focus
+                .and_then(|entity| world.get::<Children>(entity))
+                .and_then(|children| world.get::<Text>(children[0]))
+                .is_some_and(|text| text.sections[0].value == CONTINUE_TEXT)
+        };
+        for _ in 0..3 {
+            if !is_continue(harness) {
+                harness.tap(KeyCode::Down);
+            }
+        }
+        assert!(is_continue(harness));
+        harness.tap(KeyCode::Return);
+    }
+
+    #[test]
+    fn loading_continues_past_failures() {
+        let mut harness = Harness::new(AppState::Loading);
+        continue_loading(&mut harness);
+        harness.update(60);
+        assert_eq!(harness.state(), AppState::MainMenu(None));
+        assert!(harness.texts().contains(&PLAY_TEXT.to_owned()));
+    }
+
+    #[test]
+    fn loading_goes_to_launch_state() {
+        let mut harness = Harness::new(AppState::Loading);
+        harness
+            .0
+            .insert_resource(AfterLoading(AppState::InGame(None)));
+        continue_loading(&mut harness);
+        assert_eq!(harness.state(), AppState::InGame(None));
+        assert_eq!(harness.count::<With<Terrain>>(), 1);
+    }
+
+    #[test]
+    fn loading_quits_on_close() {
+        let mut harness = Harness::new(AppState::Loading);
+        harness.0.world.send_event(WindowCloseRequested {
+            id: WindowId::primary(),
+        });
+        harness.update(1);
+        assert!(!harness.0.world.resource::<Events<AppExit>>().is_empty());
+    }
+
+    #[test]
+    fn blueprint_towers() {
+        let blueprint: Blueprint = "0 1 0\n6 1 6 1\n0 1 0\n2 1 0".parse().unwrap();
+        let mut app = App::new();
+        app.add_plugin(HeadlessPlugin)
+            .insert_resource(blueprint)
+            .insert_resource(MapSeed(None));
+        add_game_plugins(&mut app, AppState::InGame(None));
+        let mut harness = Harness(app);
+        harness.update(2);
+        //Third one is on the same cell as first, and last one overlaps its footprint.
+        assert_eq!(harness.count::<Towers>(), 2);
+    }
+
+    #[test]
+    fn pause_and_resume() {
+        let mut harness = Harness::new(AppState::InGame(None));
+        harness.tap(KeyCode::Escape);
+        assert_eq!(harness.state(), AppState::InGame(Some(InGameState::Pause)));
+        //Game stays below pause.
+        assert_eq!(harness.count::<With<Terrain>>(), 1);
+        harness.tap(KeyCode::Escape);
+        assert_eq!(harness.state(), AppState::InGame(None));
+    }
+
+    #[test]
+    fn place_and_remove() {
+        let mut harness = Harness::new(AppState::InGame(None));
+        harness.click(MouseButton::Left);
+        assert_eq!(harness.count::<Towers>(), 1);
+        //Camera looks at origin, so tower is on top of grass there.
+        let grid = harness
+            .0
+            .world
+            .query::<&OccupancyGrid>()
+            .single(&harness.0.world);
+        assert!(!grid.is_free(IVec3::new(0, 1, 0)));
+
+        //Camera now looks at tower.
+        harness.click(MouseButton::Right);
+        assert_eq!(harness.count::<Towers>(), 0);
+        assert!(harness.count::<With<Debris>>() > 0);
+    }
+
+    #[test]
+    fn towers_keep_apart() {
+        let mut harness = Harness::new(AppState::InGame(None));
+        harness.click(MouseButton::Left);
+        assert_eq!(harness.count::<Towers>(), 1);
+        let look_down_at = |harness: &mut Harness, x: f32| {
+            *harness
+                .0
+                .world
+                .query_filtered::<&mut Transform, With<Camera3d>>()
+                .single_mut(&mut harness.0.world) =
+                Transform::from_xyz(x, 10., 0.).looking_at(Vec3::new(x, 0., 0.), Vec3::Z);
+            harness.click(MouseButton::Left);
+        };
+        //Center cell is free, but gun tower is wider than one cell.
+        look_down_at(&mut harness, 2.6);
+        assert_eq!(harness.count::<Towers>(), 1);
+        look_down_at(&mut harness, 6.);
+        assert_eq!(harness.count::<Towers>(), 2);
+    }
+
+    #[test]
+    fn rebound_actions() {
+        let mut harness = Harness::new(AppState::InGame(None));
+        let mut map = harness.0.world.resource_mut::<InputMap>();
+        map.rebind(InputAction::Place, KEY_SLOT, Binding::Key(KeyCode::E));
+        map.rebind(InputAction::Pause, KEY_SLOT, Binding::Key(KeyCode::P));
+        harness.click(MouseButton::Left);
+        assert_eq!(harness.count::<Towers>(), 0);
+        harness.tap(KeyCode::E);
+        assert_eq!(harness.count::<Towers>(), 1);
+        harness.tap(KeyCode::Escape);
+        assert_eq!(harness.state(), AppState::InGame(None));
+        harness.tap(KeyCode::P);
+        assert_eq!(harness.state(), AppState::InGame(Some(InGameState::Pause)));
+    }
+
+    #[test]
+    fn keys_rotate_ghost() {
+        let mut harness = Harness::new(AppState::InGame(None));
+        let rotation = |harness: &mut Harness| {
+            harness
+                .0
+                .world
+                .query_filtered::<&Transform, With<Selection>>()
+                .single(&harness.0.world)
+                .rotation
+        };
+        let before = rotation(&mut harness);
+        harness.tap(KeyCode::E);
+        let after = rotation(&mut harness);
+        assert!(before.angle_between(after) > 1.);
+        harness.tap(KeyCode::Q);
+        assert!(before.angle_between(rotation(&mut harness)) < 0.01);
+    }
+
+    #[test]
+    fn palette_selects_build() {
+        let mut harness = Harness::new(AppState::InGame(None));
+        let ghost_parts = |harness: &mut Harness| {
+            harness
+                .0
+                .world
+                .query_filtered::<&Children, With<Selection>>()
+                .single(&harness.0.world)
+                .len()
+        };
+        let selected = |harness: &Harness| harness.0.world.resource::<Palette>().selected().name;
+        assert_eq!(selected(&harness), "Gun tower");
+        assert_eq!(ghost_parts(&mut harness), 3);
+
+        harness.input(ScriptStep::Scroll(-1.));
+        assert_eq!(selected(&harness), "Block");
+        assert_eq!(ghost_parts(&mut harness), 1);
+        harness.click(MouseButton::Left);
+        let mut towers = harness.0.world.query_filtered::<&Children, Towers>();
+        assert_eq!(towers.single(&harness.0.world).len(), 1);
+
+        harness.tap(KeyCode::Key1);
+        assert_eq!(selected(&harness), "Gun tower");
+        assert_eq!(ghost_parts(&mut harness), 3);
+    }
+
+    #[test]
+    fn controls_screen() {
+        let mut harness = Harness::new(AppState::MainMenu(None));
+        harness.global().push(MainMenuState::Settings).unwrap();
+        harness.update(1);
+        harness.global().push(MainMenuState::Controls).unwrap();
+        harness.update(1);
+        assert!(harness.count::<With<ControlsButton>>() > 0);
+        harness.tap(KeyCode::Escape);
+        assert_eq!(
+            harness.state(),
+            AppState::MainMenu(Some(MainMenuState::Settings))
+        );
+        assert_eq!(harness.count::<With<ControlsButton>>(), 0);
+    }
+
+    #[test]
+    fn gamepad_plays() {
+        let mut harness = Harness::new(AppState::InGame(None));
+        harness.tap_pad(GamepadButtonType::RightTrigger2);
+        assert_eq!(harness.count::<Towers>(), 1);
+
+        //Inside dead zone.
+        let start = harness.camera();
+        harness.input(ScriptStep::Tilt(GamepadAxisType::LeftStickY, 0.1));
+        harness.update(10);
+        assert_eq!(harness.camera(), start);
+        harness.input(ScriptStep::Tilt(GamepadAxisType::LeftStickY, 1.));
+        harness.update(10);
+        harness.input(ScriptStep::Tilt(GamepadAxisType::LeftStickY, 0.));
+        let moved = harness.camera();
+        assert!(moved.translation.distance(start.translation) > 1.);
+        assert_eq!(moved.rotation, start.rotation);
+
+        harness.input(ScriptStep::Tilt(GamepadAxisType::RightStickX, 1.));
+        harness.update(10);
+        harness.input(ScriptStep::Tilt(GamepadAxisType::RightStickX, 0.));
+        assert!(harness.camera().rotation.angle_between(moved.rotation) > 0.1);
+
+        harness.tap_pad(GamepadButtonType::Start);
+        assert_eq!(harness.state(), AppState::InGame(Some(InGameState::Pause)));
+    }
+
+    #[test]
+    fn fade_blocks_sticks() {
+        let mut harness = Harness::new(AppState::InGame(None));
+        let start = harness.camera();
+        harness
+            .0
+            .world
+            .resource_mut::<ScreenFade>()
+            .start(Fade::new(1., 1.));
+        harness.input(ScriptStep::Tilt(GamepadAxisType::LeftStickY, 1.));
+        harness.update(10);
+        assert_eq!(harness.camera(), start);
+        //Stick that is still held moves camera once fade is over.
+        harness.update(120);
+        assert_ne!(harness.camera(), start);
+    }
+
+    #[test]
+    fn navigate_exit_popup() {
+        let mut harness = Harness::new(AppState::MainMenu(None));
+        harness.tap_pad(GamepadButtonType::East);
+        assert_eq!(harness.state(), AppState::AppExit);
+        let is_no = |harness: &mut Harness| {
+            let focus = harness.0.world.resource::<Focus>().0;
+            let world = &harness.0.world;
+            focus
+                .and_then(|entity| world.get::<Children>(entity))
+                .and_then(|children| world.get::<Text>(children[0]))
+                .is_some_and(|text| text.sections[0].value == NO_TEXT)
+        };
+        for _ in 0..2 {
+            if !is_no(&mut harness) {
+                harness.tap_pad(GamepadButtonType::DPadRight);
+            }
+        }
+        assert!(is_no(&mut harness));
+        harness.tap_pad(GamepadButtonType::South);
+        assert_eq!(harness.state(), AppState::MainMenu(None));
+
+        //Keys do the same.
+        harness.tap(KeyCode::Back);
+        assert_eq!(harness.state(), AppState::AppExit);
+        harness.tap(KeyCode::Down);
+        assert!(harness.0.world.resource::<Focus>().0.is_some());
+    }
+
+    #[test]
+    fn rebind_with_keys() {
+        let mut harness = Harness::new(AppState::MainMenu(None));
+        harness.global().push(MainMenuState::Settings).unwrap();
+        harness.update(1);
+        harness.global().push(MainMenuState::Controls).unwrap();
+        harness.update(1);
+        let forward = |harness: &mut Harness| {
+            harness
+                .0
+                .world
+                .resource::<InputMap>()
+                .bindings(InputAction::MoveForward)
+                .to_vec()
+        };
+        //Binding buttons come first, keyboard slots of first action before gamepad ones.
+        harness.tap(KeyCode::Down);
+        harness.tap(KeyCode::Return);
+        assert!(harness.count::<With<Disabled>>() > 0);
+        harness.tap(KeyCode::K);
+        assert_eq!(
+            forward(&mut harness),
+            [Binding::Key(KeyCode::K), Binding::Key(KeyCode::Up)]
+        );
+        assert_eq!(harness.count::<With<Disabled>>(), 0);
+
+        //Escape cancels waiting rather than being bound, and doesn't go back.
+        harness.tap(KeyCode::Return);
+        harness.tap(KeyCode::Escape);
+        assert_eq!(
+            forward(&mut harness),
+            [Binding::Key(KeyCode::K), Binding::Key(KeyCode::Up)]
+        );
+        assert_eq!(harness.count::<With<Disabled>>(), 0);
+        assert_eq!(
+            harness.state(),
+            AppState::MainMenu(Some(MainMenuState::Controls))
+        );
+
+        //Delete empties slot.
+        harness.tap(KeyCode::Return);
+        harness.tap(KeyCode::Delete);
+        assert_eq!(forward(&mut harness), [Binding::Key(KeyCode::Up)]);
+
+        //Empty gamepad slot takes only gamepad button.
+        harness.tap(KeyCode::Down);
+        harness.tap(KeyCode::Down);
+        harness.tap(KeyCode::Return);
+        harness.tap(KeyCode::J);
+        harness.tap_pad(GamepadButtonType::North);
+        assert_eq!(
+            forward(&mut harness),
+            [
+                Binding::Key(KeyCode::Up),
+                Binding::Gamepad(GamepadButtonType::North)
+            ]
+        );
+        //Which now conflicts with Move up.
+        assert!(harness
+            .texts()
+            .iter()
+            .any(|text| text.starts_with("Bound twice: Pad North")));
+
+        harness.tap(KeyCode::Escape);
+        assert_eq!(
+            harness.state(),
+            AppState::MainMenu(Some(MainMenuState::Settings))
+        );
+    }
+
+    #[test]
+    fn theme_reloads() {
+        let mut harness = Harness::new(AppState::MainMenu(None));
+        let handle = harness.0.world.resource::<Themes>()[THEME_DEFAULT].clone();
+        harness.wait_for(&handle);
+        let theme = Theme {
+            button: Color::RED,
+            text_size: 15.,
+            ..default()
+        };
+        harness
+            .0
+            .world
+            .resource_mut::<Assets<Theme>>()
+            .set_untracked(&handle, theme.clone());
+        harness.update(2);
+        assert_eq!(*harness.0.world.resource::<Theme>(), theme);
+        let mut buttons = harness
+            .0
+            .world
+            .query_filtered::<&BackgroundColor, With<Button>>();
+        assert!(buttons
+            .iter(&harness.0.world)
+            .all(|color| color.0 == Color::RED));
+        let mut texts = harness.0.world.query::<(&Text, &ThemedText)>();
+        for (text, themed) in texts.iter(&harness.0.world) {
+            assert_eq!(text.sections[0].style.font_size, themed.size / 2.);
+        }
+    }
+
+    #[test]
+    fn language_switches_texts() {
+        let mut harness = Harness::new(AppState::MainMenu(None));
+        let handle = harness.0.world.resource::<StringTables>()["de"].clone();
+        harness.wait_for(&handle);
+        harness.0.world.resource_mut::<Settings>().language = "de".to_owned();
+        harness.update(2);
+        let texts = harness.texts();
+        assert!(texts.contains(&"Spielen".to_owned()));
+        assert!(texts.contains(&"Karte: Flach".to_owned()));
+        assert!(!texts.contains(&PLAY_TEXT.to_owned()));
+
+        //Screens spawned later are translated too.
+        harness.tap(KeyCode::Escape);
+        assert!(harness.texts().contains(&"Bist du sicher?".to_owned()));
+        harness.tap(KeyCode::Escape);
+
+        harness.0.world.resource_mut::<Settings>().language = "en".to_owned();
+        harness.update(2);
+        assert!(harness.texts().contains(&PLAY_TEXT.to_owned()));
+    }
+
+    #[test]
+    fn hud_follows_game() {
+        let mut harness = Harness::new(AppState::InGame(None));
+        harness.update(1);
+        let texts = harness.texts();
+        assert!(texts.contains(&"Money: 500".to_owned()));
+        assert!(texts.contains(&"Towers: 0".to_owned()));
+        assert!(texts.contains(&"Gun tower   Cost: 100".to_owned()));
+
+        harness.click(MouseButton::Left);
+        let texts = harness.texts();
+        assert!(texts.contains(&"Towers: 1".to_owned()));
+        //Money is only shown, placing doesn't charge.
+        assert!(texts.contains(&"Money: 500".to_owned()));
+        //Camera now looks at tower.
+        assert!(texts.iter().any(|text| text.starts_with("Gun tower\n")));
+
+        //Wave start is told, and widget follows it.
+        let mut wave = harness.0.world.resource_mut::<Wave>();
+        let almost = wave.timer.duration() - FRAME;
+        wave.timer.set_elapsed(almost);
+        harness.update(2);
+        let texts = harness.texts();
+        assert!(texts.iter().any(|text| text.starts_with("Wave: 1 ")));
+        assert!(texts.contains(&WAVE_STARTED_TEXT.to_owned()));
+    }
+
+    #[test]
+    fn hud_hides_while_paused() {
+        let mut harness = Harness::new(AppState::InGame(None));
+        let visible = |harness: &mut Harness| {
+            harness
+                .0
+                .world
+                .query_filtered::<&Visibility, With<Anchored>>()
+                .iter(&harness.0.world)
+                .filter(|visibility| visibility.is_visible)
+                .count()
+        };
+        let shown = visible(&mut harness);
+        harness.tap(KeyCode::Escape);
+        assert_eq!(harness.state(), AppState::InGame(Some(InGameState::Pause)));
+        //Only crosshair, hotbar and pause popup are left.
+        assert_eq!(visible(&mut harness), 3);
+        harness.tap(KeyCode::Escape);
+        assert_eq!(visible(&mut harness), shown);
+    }
+
+    #[test]
+    fn crosshair_follows_resize() {
+        let mut harness = Harness::new(AppState::InGame(None));
+        let resize = |harness: &mut Harness, width: u32, height: u32| {
+            let mut windows = harness.0.world.resource_mut::<Windows>();
+            match windows.get_primary_mut() {
+                Some(window) => window.update_actual_size_from_backend(width, height),
+                None => windows.add(Window::new(
+                    WindowId::primary(),
+                    &WindowDescriptor::default(),
+                    width,
+                    height,
+                    1.,
+                    None,
+                    None,
+                )),
+            }
+            harness.0.world.send_event(WindowResized {
+                id: WindowId::primary(),
+                width: width as f32,
+                height: height as f32,
+            });
+            harness.update(1);
+        };
+        //Nodes have no size without ui plugin, so crosshair is placed by its corner.
+        let crosshair = |harness: &mut Harness| {
+            harness
+                .0
+                .world
+                .query_filtered::<&Style, With<UiImage>>()
+                .single(&harness.0.world)
+                .position
+        };
+        resize(&mut harness, 800, 600);
+        let position = crosshair(&mut harness);
+        assert_eq!(
+            (position.left, position.top),
+            (Val::Px(400.), Val::Px(300.))
+        );
+        resize(&mut harness, 1000, 500);
+        let position = crosshair(&mut harness);
+        assert_eq!(
+            (position.left, position.top),
+            (Val::Px(500.), Val::Px(250.))
+        );
+    }
+
+    #[test]
+    fn no_placement_while_paused() {
+        let mut harness = Harness::new(AppState::InGame(None));
+        harness.tap(KeyCode::Escape);
+        harness.click(MouseButton::Left);
+        assert_eq!(harness.count::<Towers>(), 0);
+    }
+
+    #[test]
+    fn debris_falls_to_ground() {
+        let mut harness = Harness::new(AppState::InGame(None));
+        harness.click(MouseButton::Left);
+        harness.click(MouseButton::Right);
+        //Debris settles in less than its lifetime of 5 seconds.
+        harness.update(240);
+        let mut debris = harness.0.world.query::<(&Transform, &Debris)>();
+        for (transform, _) in debris.iter(&harness.0.world) {
+            assert!(transform.translation.y < 2., "{transform:?}");
+        }
+        harness.update(120);
+        assert_eq!(harness.count::<With<Debris>>(), 0);
+    }
+
+    #[test]
+    fn debris_is_passed_through() {
+        let mut harness = Harness::new(AppState::InGame(None));
+        harness.tap(KeyCode::Key2);
+        harness.click(MouseButton::Left);
+        harness.click(MouseButton::Right);
+        let mut debris = harness.0.world.query::<(&Collider, &Debris)>();
+        let (collider, _) = debris.single(&harness.0.world);
+        //Block is a unit cube.
+        assert!(matches!(
+            collider.shape(),
+            Shape::Cuboid { half_size, .. } if half_size == Vec3::splat(0.5)
+        ));
+        for _ in 0..60 {
+            harness.update(1);
+            let mut look_at = harness.0.world.query::<&LookAt>();
+            let hit = look_at
+                .single(&harness.0.world)
+                .0
+                .as_ref()
+                .map(|hit| hit.entity);
+            assert!(hit.is_none_or(|entity| !harness.0.world.entity(entity).contains::<Debris>()));
+        }
+    }
+
+    #[test]
+    fn replace_clears_state() {
+        let mut harness = Harness::new(AppState::InGame(None));
+        harness.click(MouseButton::Left);
+        harness.replace(AppState::MainMenu(None));
+        assert_eq!(harness.state(), AppState::MainMenu(None));
+        assert_eq!(harness.count::<With<Terrain>>(), 0);
+        assert_eq!(harness.count::<Towers>(), 0);
+    }
+
+    #[test]
+    fn mark_scopes() {
+        let mut harness = Harness::new(AppState::InGame(None));
+        let global = harness.0.world.resource::<GlobalState>();
+        let (kept, cleared, exit_of_pause) = (
+            global.mark().persist([AppState::MainMenu(None)]),
+            global.mark(),
+            global.mark().until_exit_of(1),
+        );
+        let kept = harness.0.world.spawn(kept).id();
+        let cleared = harness.0.world.spawn(cleared).id();
+        let exit_of_pause = harness.0.world.spawn(exit_of_pause).id();
+        let exists = |harness: &Harness, entity| harness.0.world.get_entity(entity).is_some();
+        let pause_visible = |harness: &mut Harness| {
+            let mut popups = harness.0.world.query::<(&StateMark, &Visibility)>();
+            popups
+                .iter(&harness.0.world)
+                .find(|(mark, _)| mark.state() == AppState::InGame(Some(InGameState::Pause)))
+                .map(|(_, visibility)| visibility.is_visible)
+        };
+
+        //Pause popup hides below settings instead of being despawned.
+        harness.tap(KeyCode::Escape);
+        assert_eq!(pause_visible(&mut harness), Some(true));
+        let popped = harness.global().mark().until_pop();
+        let popped = harness.0.world.spawn(popped).id();
+        harness.global().push(InGameState::Settings).unwrap();
+        harness.update(1);
+        assert_eq!(pause_visible(&mut harness), Some(false));
+        harness.tap(KeyCode::Escape);
+        assert_eq!(pause_visible(&mut harness), Some(true));
+        //Pop above its own hierarchy keeps it, as does exit of other depth.
+        assert!(exists(&harness, popped));
+        assert!(exists(&harness, exit_of_pause));
+
+        //Resuming pops pause, and exits depth 1.
+        harness.tap(KeyCode::Escape);
+        assert!(!exists(&harness, popped));
+        assert!(!exists(&harness, exit_of_pause));
+        assert!(exists(&harness, cleared));
+
+        //Pop only entity survives replace.
+        harness.tap(KeyCode::Escape);
+        let survivor = harness.global().mark().until_pop();
+        let survivor = harness.0.world.spawn(survivor).id();
+
+        //Persisting entity survives replace to listed state.
+        harness.replace(AppState::MainMenu(None));
+        assert_eq!(pause_visible(&mut harness), None);
+        assert!(exists(&harness, kept));
+        assert!(!exists(&harness, cleared));
+        assert!(exists(&harness, survivor));
+    }
+
+    #[test]
+    fn queued_transitions() {
+        let mut harness = Harness::new(AppState::InGame(None));
+        //Requests on same frame are applied one by one.
+        harness.global().push(InGameState::Pause).unwrap();
+        harness.global().push(InGameState::Settings).unwrap();
+        harness.update(1);
+        assert_eq!(harness.state(), AppState::InGame(Some(InGameState::Pause)));
+        harness.update(1);
+        assert_eq!(
+            harness.state(),
+            AppState::InGame(Some(InGameState::Settings))
+        );
+    }
+
+    #[test]
+    fn settings_screen() {
+        let mut harness = Harness::new(AppState::InGame(None));
+        harness.tap(KeyCode::Escape);
+        harness.global().push(InGameState::Settings).unwrap();
+        harness.update(1);
+        assert_eq!(
+            harness.state(),
+            AppState::InGame(Some(InGameState::Settings))
+        );
+        let buttons = harness.count::<With<SettingsButton>>();
+        assert!(buttons > 0);
+        harness.0.world.resource_mut::<Settings>().move_speed = 20.;
+        harness.tap(KeyCode::Escape);
+        assert_eq!(harness.state(), AppState::InGame(Some(InGameState::Pause)));
+        assert_eq!(harness.count::<With<SettingsButton>>(), 0);
+
+        //Same screen above main menu.
+        harness.replace(AppState::MainMenu(None));
+        harness.global().push(MainMenuState::Settings).unwrap();
+        harness.update(1);
+        assert_eq!(harness.count::<With<SettingsButton>>(), buttons);
+        assert_eq!(harness.0.world.resource::<Settings>().move_speed, 20.);
+    }
+
+    #[test]
+    fn exit_popup_unwinds() {
+        let mut harness = Harness::new(AppState::InGame(None));
+        harness.tap(KeyCode::Escape);
+        harness.global().push_exit().unwrap();
+        harness.update(1);
+        assert_eq!(harness.state(), AppState::AppExit);
+        harness.tap(KeyCode::Escape);
+        assert_eq!(harness.state(), AppState::InGame(Some(InGameState::Pause)));
+    }
+
+    #[test]
+    fn smoke_script_exits() {
+        let mut harness = Harness::new(AppState::InGame(None));
+        *harness.0.world.resource_mut::<InputScript>() = InputScript::smoke();
+        let mut frames = 0;
+        while harness.0.world.resource::<Events<AppExit>>().is_empty() {
+            assert!(frames < 200, "Script didn't exit");
+            harness.update(1);
+            frames += 1;
+        }
+        assert_eq!(harness.state(), AppState::InGame(None));
+        assert_eq!(harness.count::<Towers>(), 0);
+    }
+}
@@ -0,0 +1,147 @@
+use crate::settings::Settings;
+
+use bevy::{
+    prelude::*,
+    ui::{UiScale, UiSystem},
+    window::{WindowResized, WindowScaleFactorChanged},
+};
+
+///Point of screen, and of node, that are put on each other.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Center,
+    BottomLeft,
+    Bottom,
+}
+
+impl Anchor {
+    ///Where anchor is, from 0 at left and top to 1 at right and bottom.
+    fn fraction(self) -> Vec2 {
+        match self {
+            Anchor::TopLeft => Vec2::new(0., 0.),
+            Anchor::Top => Vec2::new(0.5, 0.),
+            Anchor::TopRight => Vec2::new(1., 0.),
+            Anchor::Center => Vec2::new(0.5, 0.5),
+            Anchor::BottomLeft => Vec2::new(0., 1.),
+            Anchor::Bottom => Vec2::new(0.5, 1.),
+        }
+    }
+}
+
+///Root node that stays at anchor of window however it is resized or scaled.
+/// - Offset is in ui pixels, x to right and y down, so nodes at right or bottom need negative one.
+/// - Position of style is overwritten, and size of node is whatever its style makes it.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Anchored {
+    pub anchor: Anchor,
+    pub offset: Vec2,
+}
+
+impl Anchored {
+    pub fn new(anchor: Anchor, offset: Vec2) -> Self {
+        Self { anchor, offset }
+    }
+
+    ///Top left corner of node of given size in screen of given size.
+    fn position(&self, screen: Vec2, size: Vec2) -> Vec2 {
+        (screen - size) * self.anchor.fraction() + self.offset
+    }
+}
+
+///Batch setup of layout that follows window.
+/// - Ui scale of settings multiplies scale factor of window.
+/// - Anchored nodes are placed again when window is resized or rescaled, or their size changes.
+pub struct LayoutPlugin;
+
+impl Plugin for LayoutPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(CoreStage::PostUpdate, apply_ui_scale)
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                place_anchored.after(apply_ui_scale).before(UiSystem::Flex),
+            );
+    }
+}
+
+///Scales ui by settings. There's no ui scale without ui plugin, like in headless mode.
+fn apply_ui_scale(settings: Res<Settings>, ui_scale: Option<ResMut<UiScale>>) {
+    let Some(mut ui_scale) = ui_scale else {
+        return;
+    };
+    let scale = settings.ui_scale as f64;
+    if settings.is_changed() && ui_scale.scale != scale {
+        ui_scale.scale = scale;
+    }
+}
+
+///Sets position of anchored nodes from size of window in ui pixels.
+/// - Size of node is the one of last layout, so node that changes size follows a frame later.
+fn place_anchored(
+    windows: Res<Windows>,
+    ui_scale: Option<Res<UiScale>>,
+    mut resized: EventReader<WindowResized>,
+    mut rescaled: EventReader<WindowScaleFactorChanged>,
+    mut nodes: Query<(
+        &Node,
+        ChangeTrackers<Node>,
+        &Anchored,
+        ChangeTrackers<Anchored>,
+        &mut Style,
+    )>,
+) {
+    let Some(window) = windows.get_primary() else {
+        return;
+    };
+    let scale = ui_scale
+        .as_ref()
+        .map_or(1., |ui_scale| ui_scale.scale as f32);
+    let all = resized.iter().count() > 0
+        || rescaled.iter().count() > 0
+        || ui_scale.is_some_and(|ui_scale| ui_scale.is_changed());
+    let screen = Vec2::new(window.width(), window.height()) / scale;
+    for (node, node_changes, anchored, anchored_changes, mut style) in nodes.iter_mut() {
+        if !all && !node_changes.is_changed() && !anchored_changes.is_changed() {
+            continue;
+        }
+        let position = anchored.position(screen, node.size());
+        let rect = UiRect {
+            left: Val::Px(position.x),
+            top: Val::Px(position.y),
+            ..default()
+        };
+        //Keep change detection quiet, since layout reruns on style change.
+        if style.position_type != PositionType::Absolute || style.position != rect {
+            style.position_type = PositionType::Absolute;
+            style.position = rect;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn anchored_position() {
+        let screen = Vec2::new(800., 600.);
+        let size = Vec2::new(100., 50.);
+        let at = |anchor, offset| Anchored::new(anchor, offset).position(screen, size);
+        assert_eq!(
+            at(Anchor::TopLeft, Vec2::new(12., 12.)),
+            Vec2::new(12., 12.)
+        );
+        assert_eq!(at(Anchor::Center, Vec2::ZERO), Vec2::new(350., 275.));
+        assert_eq!(
+            at(Anchor::TopRight, Vec2::new(-12., 12.)),
+            Vec2::new(688., 12.)
+        );
+        assert_eq!(
+            at(Anchor::BottomLeft, Vec2::new(12., -12.)),
+            Vec2::new(12., 538.)
+        );
+        assert_eq!(at(Anchor::Bottom, Vec2::ZERO), Vec2::new(350., 550.));
+    }
+}
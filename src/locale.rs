@@ -0,0 +1,194 @@
+use crate::{
+    asset::{Fonts, StringTables, FONT_FALLBACK, FONT_SCHLUBER},
+    settings::Settings,
+    theme::ThemedText,
+};
+
+use ab_glyph::Font as _;
+
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::{BoxedFuture, HashMap},
+};
+
+use serde::Deserialize;
+
+///Language that strings are written in, which needs no table.
+pub const SOURCE_LANGUAGE: &str = "en";
+
+///Code and own name of every language, in order picker cycles them.
+pub const LANGUAGES: [(&str, &str); 3] = [
+    (SOURCE_LANGUAGE, "English"),
+    ("de", "Deutsch"),
+    ("ru", "Русский"),
+];
+
+///Translations of one language. Keys are English text.
+#[derive(Clone, Debug, Default, Deserialize, TypeUuid)]
+#[uuid = "2d1b7d0e-5c55-4f3c-9f0a-4c1f4ad5e1a7"]
+#[serde(transparent)]
+pub struct StringTable(HashMap<String, String>);
+
+///Strings of current language.
+#[derive(Resource, Default)]
+pub struct Locale(StringTable);
+
+impl Locale {
+    ///Translation of English text. Text without one stays English.
+    pub fn get<'a>(&'a self, text: &'a str) -> &'a str {
+        self.0 .0.get(text).map_or(text, String::as_str)
+    }
+}
+
+///Text that shows translation of English text, and follows language change.
+#[derive(Component)]
+pub struct Localized(pub &'static str);
+
+///Own name of language, or code for unknown one.
+pub fn language_name(code: &str) -> &str {
+    LANGUAGES
+        .iter()
+        .find(|(language, _)| *language == code)
+        .map_or(code, |(_, name)| name)
+}
+
+///Language next to given one, wrapping around.
+pub fn cycle_language(code: &str, step: isize) -> &'static str {
+    let index = LANGUAGES
+        .iter()
+        .position(|(language, _)| *language == code)
+        .unwrap_or(0) as isize;
+    LANGUAGES[(index + step).rem_euclid(LANGUAGES.len() as isize) as usize].0
+}
+
+///Reads `.lang.ron` files.
+#[derive(Default)]
+struct StringTableLoader;
+
+impl AssetLoader for StringTableLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let table = ron::de::from_bytes::<StringTable>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(table));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["lang.ron"]
+    }
+}
+
+///Batch setup of localization.
+/// - Language is part of settings, and switching it updates every spawned text.
+/// - Text falls back to other font when main one lacks its letters.
+pub struct LocalePlugin;
+
+impl Plugin for LocalePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<StringTable>()
+            .init_asset_loader::<StringTableLoader>()
+            .init_resource::<Locale>()
+            .add_system_to_stage(CoreStage::PreUpdate, switch_language)
+            .add_system_to_stage(CoreStage::PostUpdate, localize_texts)
+            .add_system_to_stage(CoreStage::PostUpdate, fallback_fonts.after(localize_texts));
+    }
+}
+
+///Loads table of language in settings when either changes.
+fn switch_language(
+    settings: Res<Settings>,
+    mut events: EventReader<AssetEvent<StringTable>>,
+    tables: Res<StringTables>,
+    assets: Res<Assets<StringTable>>,
+    mut locale: ResMut<Locale>,
+    mut last: Local<Option<String>>,
+) {
+    let reloaded = events.iter().count() > 0;
+    if !reloaded && last.as_deref() == Some(settings.language.as_str()) {
+        return;
+    }
+    let table = tables
+        .iter()
+        .find(|(code, _)| **code == settings.language)
+        .and_then(|(_, handle)| assets.get(handle));
+    //Table not loaded yet is tried again on its event.
+    if table.is_none() && settings.language != SOURCE_LANGUAGE {
+        debug!("No strings for language {} yet", settings.language);
+    }
+    locale.0 = table.cloned().unwrap_or_default();
+    *last = Some(settings.language.clone());
+}
+
+///Sets translation on spawned texts, and on every text when language changes.
+fn localize_texts(locale: Res<Locale>, mut texts: Query<(&mut Text, &Localized)>) {
+    for (mut text, localized) in texts.iter_mut() {
+        if locale.is_changed() || text.is_added() {
+            let value = locale.get(localized.0);
+            if text.sections[0].value != value {
+                text.sections[0].value = value.to_owned();
+            }
+        }
+    }
+}
+
+///Uses fallback font for text that main font can't show.
+fn fallback_fonts(
+    fonts: Res<Fonts>,
+    font_assets: Res<Assets<Font>>,
+    mut texts: Query<&mut Text, (Changed<Text>, With<ThemedText>)>,
+) {
+    let (Some(main), Some(fallback)) = (fonts.get(FONT_SCHLUBER), fonts.get(FONT_FALLBACK)) else {
+        return;
+    };
+    let Some(main_font) = font_assets.get(main) else {
+        return;
+    };
+    let covers = |value: &str| {
+        value
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .all(|c| main_font.font.glyph_id(c).0 != 0)
+    };
+    for mut text in texts.iter_mut() {
+        let font = match text.sections.iter().all(|section| covers(&section.value)) {
+            true => main,
+            false => fallback,
+        };
+        if text
+            .sections
+            .iter()
+            .any(|section| section.style.font != *font)
+        {
+            for section in text.sections.iter_mut() {
+                section.style.font = font.clone();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tables_parse() {
+        for (code, _) in &LANGUAGES[1..] {
+            let path = format!("{}/assets/lang/{code}.lang.ron", env!("CARGO_MANIFEST_DIR"));
+            let table: StringTable = ron::from_str(&std::fs::read_to_string(path).unwrap())
+                .unwrap_or_else(|err| panic!("{code}: {err}"));
+            let locale = Locale(table);
+            assert_ne!(locale.get("Play"), "Play");
+            //Missing key falls back to English.
+            assert_eq!(locale.get("Not translated"), "Not translated");
+        }
+        assert_eq!(cycle_language(SOURCE_LANGUAGE, -1), "ru");
+        assert_eq!(language_name("xx"), "xx");
+    }
+}
@@ -1,39 +1,158 @@
 pub(crate) mod asset;
+pub(crate) mod blueprint;
+pub(crate) mod cli;
 pub(crate) mod consts;
+pub(crate) mod controls;
 pub(crate) mod func;
-pub(crate) mod macros;
+pub(crate) mod headless;
+pub(crate) mod layout;
+pub(crate) mod locale;
 pub(crate) mod physics;
+pub(crate) mod settings;
 pub(crate) mod states;
+pub(crate) mod terrain;
+pub(crate) mod theme;
 pub(crate) mod ui;
 
 use crate::{
     asset::AssetManagingPlugin,
-    states::{in_game::*, main_menu::*, *},
+    blueprint::Blueprint,
+    cli::{Cli, USAGE},
+    controls::{ControlsPlugin, InputMap, CONTROLS_FILE},
+    headless::{HeadlessPlugin, InputScript},
+    layout::LayoutPlugin,
+    locale::LocalePlugin,
+    physics::PhysicsPlugin,
+    settings::{ConfigDir, Settings, SettingsPlugin, SETTINGS_FILE},
+    states::{
+        controls::*, hud::*, in_game::*, loading::*, main_menu::*, palette::*, pause::*,
+        settings::*, *,
+    },
+    terrain::{generate::MapSeed, TerrainPlugin},
+    theme::ThemePlugin,
+    ui::ButtonPlugin,
 };
 
-use bevy::prelude::*;
+use bevy::{log::LogPlugin, prelude::*};
 
 use bevy_polyline::PolylinePlugin;
 
 fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins.set(WindowPlugin {
-            window: WindowDescriptor {
-                title: "Game made with Rust".to_owned(),
-                ..default()
-            },
-            close_when_requested: false,
+    let cli = match Cli::parse() {
+        Ok(cli) => cli,
+        Err(err) => {
+            eprintln!("error: {err}\n\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
+    if cli.help {
+        println!("{USAGE}");
+        return;
+    }
+    //Fail before any window shows up.
+    let blueprint = match cli.load.as_ref().map(Blueprint::load).transpose() {
+        Ok(blueprint) => blueprint,
+        Err(err) => {
+            eprintln!("error: can't load blueprint: {err}");
+            std::process::exit(1);
+        }
+    };
+    let log = LogPlugin {
+        level: cli.log_level.unwrap_or(LogPlugin::default().level),
+        ..default()
+    };
+
+    let mut app = App::new();
+    if cli.headless {
+        //No window nor render. Plays scripted input.
+        app.add_plugin(log)
+            .add_plugin(HeadlessPlugin)
+            .insert_resource(InputScript::smoke());
+    } else {
+        //Settings of last run. Headless mode keeps defaults, so scripts run the same everywhere.
+        let config = ConfigDir::user();
+        let settings: Settings = config.load_or_default(SETTINGS_FILE);
+        let map = config
+            .load_or_default::<InputMap>(CONTROLS_FILE)
+            .fill_defaults();
+        let mut window = WindowDescriptor {
+            title: "Game made with Rust".to_owned(),
             ..default()
-        }))
-        //Asset manage helpers
-        .add_plugin(AssetManagingPlugin)
+        };
+        settings.apply_to(&mut window);
+        if let Some((width, height)) = cli.window {
+            window.width = width;
+            window.height = height;
+        }
+        app.insert_resource(settings)
+            .insert_resource(map)
+            .insert_resource(config);
+        app.add_plugins(
+            DefaultPlugins
+                .set(WindowPlugin {
+                    window,
+                    close_when_requested: false,
+                    ..default()
+                })
+                .set(log)
+                //Theme reloads when its file is saved.
+                .set(AssetPlugin {
+                    watch_for_changes: cfg!(feature = "dev"),
+                    ..default()
+                }),
+        )
         //Polyline lib
-        .add_plugin(PolylinePlugin)
+        .add_plugin(PolylinePlugin);
+    }
+    add_game_plugins(&mut app, cli.initial_state());
+    if let Some(state) = cli.after_loading() {
+        app.insert_resource(AfterLoading(state));
+    }
+    if let Some(seed) = cli.seed {
+        app.insert_resource(MapSeed(seed));
+    }
+    if let Some(blueprint) = blueprint {
+        app.insert_resource(blueprint);
+    }
+    app.run();
+}
+
+///Plugins of game itself, which both windowed and headless app run.
+pub(crate) fn add_game_plugins(app: &mut App, initial: AppState) {
+    //Asset manage helpers
+    app.add_plugin(AssetManagingPlugin)
+        //Ui look
+        .add_plugin(ThemePlugin)
+        //Player options
+        .add_plugin(SettingsPlugin)
+        //Ui language
+        .add_plugin(LocalePlugin)
+        //Input actions
+        .add_plugin(ControlsPlugin)
+        //Ui scale, and placement that follows window
+        .add_plugin(LayoutPlugin)
+        //Buttons, and menu navigation by keys and gamepad
+        .add_plugin(ButtonPlugin)
+        //Spatial queries
+        .add_plugin(PhysicsPlugin)
+        //Voxel terrain
+        .add_plugin(TerrainPlugin)
         //Global states manager
-        .add_plugin(StatesPlugin)
+        .add_plugin(StatesPlugin { initial })
+        //Loading
+        .add_plugin(LoadingPlugin)
         //Main Menu
         .add_plugin(MainMenuPlugin)
         //In Game
         .add_plugin(InGamePlugin)
-        .run();
+        //Build palette and hotbar
+        .add_plugin(PalettePlugin)
+        //In game HUD
+        .add_plugin(HudPlugin)
+        //Pause menu
+        .add_plugin(PausePlugin)
+        //Settings screen
+        .add_plugin(SettingsMenuPlugin)
+        //Controls screen
+        .add_plugin(ControlsMenuPlugin);
 }
@@ -109,6 +109,14 @@ impl AABB {
         }
     }
 
+    ///Smallest bounding box that covers both.
+    pub fn merge(&self, other: &Self) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
     ///Determines which octant from origin this box is placed. True is positive, false is negative.
     pub fn octant(&self) -> Option<BVec3> {
         let x_p = self.min.x >= 0. && self.max.x > 0.;
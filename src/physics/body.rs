@@ -0,0 +1,60 @@
+use crate::{physics::collider::Collider, terrain::Terrain};
+
+use bevy::prelude::*;
+
+pub const GRAVITY: Vec3 = Vec3::new(0., -9.81, 0.);
+///Ratio of vertical speed kept when bouncing on ground.
+const RESTITUTION: f32 = 0.3;
+///Ratio of horizontal and angular speed kept on every ground contact.
+const FRICTION: f32 = 0.8;
+///Speed under which body on ground stops for good.
+const SETTLE_SPEED: f32 = 0.3;
+
+///Linear and angular velocity of body that physics moves.
+#[derive(Component, Default)]
+pub struct Velocity {
+    pub linear: Vec3,
+    ///Axis scaled by radians per second.
+    pub angular: Vec3,
+}
+
+///Mark body that came to rest on ground, so it isn't moved anymore.
+#[derive(Component)]
+pub struct Settled;
+
+///Moves bodies by gravity and stops them on terrain.
+pub fn integrate_bodies(
+    mut commands: Commands,
+    mut bodies: Query<(Entity, &mut Transform, &mut Velocity, &Collider), Without<Settled>>,
+    terrain: Query<&Terrain>,
+    time: Res<Time>,
+) {
+    let delta = time.delta_seconds();
+    let terrain = terrain.get_single().ok();
+    for (entity, mut transform, mut velocity, collider) in bodies.iter_mut() {
+        velocity.linear += GRAVITY * delta;
+        transform.translation += velocity.linear * delta;
+        transform.rotation =
+            (Quat::from_scaled_axis(velocity.angular * delta) * transform.rotation).normalize();
+
+        let ground = match terrain {
+            Some(terrain) => terrain.ground(transform.translation),
+            None => 0.,
+        };
+        let penetration = ground - collider.aabb(&transform).min().y;
+        if penetration > 0. {
+            //Push out of ground and bounce.
+            transform.translation.y += penetration;
+            if velocity.linear.y < 0. {
+                velocity.linear.y *= -RESTITUTION;
+            }
+            velocity.linear.x *= FRICTION;
+            velocity.linear.z *= FRICTION;
+            velocity.angular *= FRICTION;
+            if velocity.linear.length() < SETTLE_SPEED {
+                *velocity = Velocity::default();
+                commands.entity(entity).insert(Settled);
+            }
+        }
+    }
+}
@@ -18,6 +18,17 @@ impl Collider {
     pub fn shape(&self) -> Shape {
         self.shape.clone()
     }
+
+    ///Grid cells whose center is inside of aabb.
+    pub fn footprint(&self, transform: &Transform) -> impl Iterator<Item = IVec3> {
+        let aabb = self.aabb(transform);
+        //Cells exactly on bound line are excluded.
+        let min = aabb.min().floor().as_ivec3() + 1;
+        let max = aabb.max().ceil().as_ivec3() - 1;
+        (min.z..=max.z).flat_map(move |z| {
+            (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| IVec3::new(x, y, z)))
+        })
+    }
 }
 
 #[derive(Clone)]
@@ -30,6 +41,11 @@ pub enum Shape {
         radius: f32,
         cut: f32,
     },
+    ///Box in local space, like bound of mesh.
+    Cuboid {
+        center: Vec3,
+        half_size: Vec3,
+    },
 }
 
 impl Shape {
@@ -37,6 +53,7 @@ impl Shape {
         match self {
             Shape::Sphere { radius } => sphere_aabb(*radius, transform),
             Shape::CutSphere { radius, cut } => cut_sphere_aabb(*radius, *cut, transform),
+            Shape::Cuboid { center, half_size } => cuboid_aabb(*center, *half_size, transform),
         }
     }
 }
@@ -55,3 +72,14 @@ fn cut_sphere_aabb(radius: f32, cut: f32, transform: &Transform) -> AABB {
         transform.transform_point(Vec3::new(0., 0., -radius)),
     ])
 }
+
+fn cuboid_aabb(center: Vec3, half_size: Vec3, transform: &Transform) -> AABB {
+    let corners = [-1., 1.].into_iter().flat_map(|x| {
+        [-1., 1.].into_iter().flat_map(move |y| {
+            [-1., 1.]
+                .into_iter()
+                .map(move |z| transform.transform_point(center + half_size * Vec3::new(x, y, z)))
+        })
+    });
+    AABB::from_points(&corners.collect::<Vec<_>>())
+}
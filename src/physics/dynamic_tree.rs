@@ -0,0 +1,235 @@
+use crate::physics::{
+    aabb::AABB,
+    ray::{Ray, RayHitInfo},
+};
+
+use bevy::prelude::*;
+
+///Bounding volume hierarchy for entities that move every tick.
+/// - Unlike Octree, this is thrown away and rebuilt rather than updated incrementally.
+/// - Nodes are stored flat and entities are sorted so that each node owns a contiguous range.
+#[derive(Default)]
+pub struct DynamicTree {
+    entities: Vec<(Entity, AABB)>,
+    nodes: Vec<DynamicNode>,
+}
+
+impl DynamicTree {
+    ///Max entities in a leaf before it is split.
+    const LEAF_SIZE: usize = 4;
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    ///Replaces whole contents with given entities and builds nodes again.
+    pub fn rebuild(&mut self, entities: impl IntoIterator<Item = (Entity, AABB)>) {
+        self.entities.clear();
+        self.entities.extend(entities);
+        self.nodes.clear();
+        if !self.entities.is_empty() {
+            self.build_node(0, self.entities.len());
+        }
+    }
+
+    ///Refits bounds of existing nodes without changing topology.
+    ///Cheaper than rebuild, but quality drops as entities drift apart.
+    pub fn refit(&mut self, mut f: impl FnMut(Entity) -> Option<AABB>) {
+        for (entity, aabb) in self.entities.iter_mut() {
+            if let Some(new) = f(*entity) {
+                *aabb = new;
+            }
+        }
+        //Children are always pushed after their parent, so reverse order visits them first.
+        for index in (0..self.nodes.len()).rev() {
+            let node = &self.nodes[index];
+            let aabb = if node.is_leaf() {
+                Self::bound(&self.entities[node.start..node.start + node.len])
+            } else {
+                self.nodes[node.left]
+                    .aabb
+                    .merge(&self.nodes[node.right].aabb)
+            };
+            self.nodes[index].aabb = aabb;
+        }
+    }
+
+    ///Builds node of entities in range recursively and returns its index.
+    fn build_node(&mut self, start: usize, len: usize) -> usize {
+        let slice = &mut self.entities[start..start + len];
+        let aabb = Self::bound(slice);
+        let index = self.nodes.len();
+        self.nodes.push(DynamicNode {
+            aabb,
+            start,
+            len,
+            left: DynamicNode::NULL_INDEX,
+            right: DynamicNode::NULL_INDEX,
+        });
+        if len > Self::LEAF_SIZE {
+            //Split at median along the longest axis.
+            let length = aabb.length();
+            let axis = if length.x >= length.y && length.x >= length.z {
+                0
+            } else if length.y >= length.z {
+                1
+            } else {
+                2
+            };
+            let mid = len / 2;
+            slice.select_nth_unstable_by(mid, |(_, a), (_, b)| {
+                a.center()[axis].total_cmp(&b.center()[axis])
+            });
+            let left = self.build_node(start, mid);
+            let right = self.build_node(start + mid, len - mid);
+            let node = &mut self.nodes[index];
+            node.left = left;
+            node.right = right;
+        }
+        index
+    }
+
+    ///Bound that covers all given entities. Slice must not be empty.
+    fn bound(entities: &[(Entity, AABB)]) -> AABB {
+        entities[1..]
+            .iter()
+            .fold(entities[0].1, |acc, (_, aabb)| acc.merge(aabb))
+    }
+
+    ///Iterating entities that intersects with given bounding box.
+    pub fn _intersect(&self, aabb: AABB, f: impl Fn(&Entity)) {
+        if self.nodes.is_empty() {
+            return;
+        }
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !node.aabb._intersects(&aabb) {
+                continue;
+            }
+            if node.is_leaf() {
+                for (entity, entity_aabb) in &self.entities[node.start..node.start + node.len] {
+                    if entity_aabb._intersects(&aabb) {
+                        f(entity);
+                    }
+                }
+            } else {
+                stack.push(node.left);
+                stack.push(node.right);
+            }
+        }
+    }
+
+    ///Return hit information about raycast. Entities that filter refuses are passed through.
+    pub fn raycast(&self, ray: &Ray, filter: impl Fn(Entity) -> bool) -> Option<RayHitInfo> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+        let mut ret: Option<RayHitInfo> = None;
+        let mut len = f32::INFINITY;
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            //Skip nodes that are further than current nearest hit.
+            match node.aabb.intersects_ray_raw(ray) {
+                Some((t_min, _)) if t_min < len => {}
+                _ => continue,
+            }
+            if node.is_leaf() {
+                let entities = &self.entities[node.start..node.start + node.len];
+                for (entity, aabb) in entities.iter().filter(|(e, _)| filter(*e)) {
+                    if let Some(candidate) = aabb.intersects_ray(ray) {
+                        if candidate < len {
+                            len = candidate;
+                            ret = Some(RayHitInfo::new(*entity, *aabb, len));
+                        }
+                    }
+                }
+            } else {
+                stack.push(node.left);
+                stack.push(node.right);
+            }
+        }
+        ret
+    }
+}
+
+struct DynamicNode {
+    ///Bound of every entity in range.
+    aabb: AABB,
+    ///Range of entities this node covers.
+    start: usize,
+    len: usize,
+    left: usize,
+    right: usize,
+}
+
+impl DynamicNode {
+    const NULL_INDEX: usize = usize::MAX;
+
+    fn is_leaf(&self) -> bool {
+        self.left == Self::NULL_INDEX
+    }
+}
+
+///Marks entity that moves every tick, so it goes to DynamicTree rather than Octree.
+#[derive(Component)]
+pub struct Dynamic;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::RefCell;
+
+    ///Unit cube around point.
+    fn cube(center: Vec3) -> AABB {
+        AABB::from_size_offset(1., center)
+    }
+
+    ///Row of cubes along x, enough to be split into several leaves.
+    fn row() -> DynamicTree {
+        let mut tree = DynamicTree::default();
+        tree.rebuild(
+            (0..10).map(|i| (Entity::from_raw(i), cube(Vec3::new(i as f32 * 2., 0., 0.)))),
+        );
+        tree
+    }
+
+    fn intersected(tree: &DynamicTree, aabb: AABB) -> Vec<Entity> {
+        let found = RefCell::new(Vec::new());
+        tree._intersect(aabb, |entity| found.borrow_mut().push(*entity));
+        found.into_inner()
+    }
+
+    #[test]
+    fn raycast_hits_nearest() {
+        let tree = row();
+        assert!(tree.nodes.len() > 1);
+        let ray = Ray::new(Vec3::new(-5., 0., 0.), Vec3::X);
+        let hit = tree.raycast(&ray, |_| true).unwrap();
+        assert_eq!(hit.entity, Entity::from_raw(0));
+        assert_eq!(hit.t, 4.5);
+        //Filtered entity is passed through.
+        let hit = tree
+            .raycast(&ray, |entity| entity != Entity::from_raw(0))
+            .unwrap();
+        assert_eq!(hit.entity, Entity::from_raw(1));
+        let ray = Ray::new(Vec3::new(-5., 0., 0.), -Vec3::X);
+        assert!(tree.raycast(&ray, |_| true).is_none());
+    }
+
+    #[test]
+    fn refit_follows_movement() {
+        let mut tree = row();
+        let moved = Entity::from_raw(3);
+        let to = Vec3::new(0., 10., 0.);
+        tree.refit(|entity| (entity == moved).then(|| cube(to)));
+        assert_eq!(tree.len(), 10);
+        assert_eq!(intersected(&tree, cube(to)), [moved]);
+        assert!(!intersected(&tree, cube(Vec3::new(6., 0., 0.))).contains(&moved));
+        //Root grows to cover it, so ray from above finds it.
+        let ray = Ray::new(Vec3::new(0., 20., 0.), -Vec3::Y);
+        assert_eq!(tree.raycast(&ray, |_| true).unwrap().entity, moved);
+    }
+}
@@ -1,4 +1,22 @@
 pub mod aabb;
+pub mod body;
 pub mod collider;
+pub mod dynamic_tree;
+pub mod occupancy;
 pub mod octree;
 pub mod ray;
+pub mod spatial;
+
+use crate::physics::spatial::update_dynamic_tree;
+
+use bevy::prelude::*;
+
+///Batch setup of physics.
+pub struct PhysicsPlugin;
+
+impl Plugin for PhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        //Movers are moved in Update, so refresh after them for the next tick's queries.
+        app.add_system_to_stage(CoreStage::PostUpdate, update_dynamic_tree);
+    }
+}
@@ -0,0 +1,194 @@
+use crate::physics::{aabb::AABB, collider::Collider};
+
+use bevy::prelude::*;
+
+///Integer aligned occupancy of a volume, one cell per unit.
+/// - Cell `IVec3` is the rounded position of its center.
+/// - Occupancy is a bitset, and each occupied cell remembers which entity claimed it first.
+/// - Cells outside of volume are never free.
+#[derive(Component)]
+pub struct OccupancyGrid {
+    ///Cell at minimum corner.
+    origin: IVec3,
+    size: IVec3,
+    bits: Vec<u64>,
+    owners: Vec<Option<Entity>>,
+}
+
+impl OccupancyGrid {
+    ///Covers every cell whose center is inside given bound.
+    pub fn new(aabb: AABB) -> Self {
+        let origin = aabb.min().floor().as_ivec3() + 1;
+        let size = (aabb.max().ceil().as_ivec3() - origin).max(IVec3::ZERO);
+        let len = (size.x * size.y * size.z) as usize;
+        Self {
+            origin,
+            size,
+            bits: vec![0; len.div_ceil(64)],
+            owners: vec![None; len],
+        }
+    }
+
+    ///Cell that contains point.
+    pub fn cell(point: Vec3) -> IVec3 {
+        point.round().as_ivec3()
+    }
+
+    fn index(&self, cell: IVec3) -> Option<usize> {
+        let local = cell - self.origin;
+        if local.cmplt(IVec3::ZERO).any() || local.cmpge(self.size).any() {
+            None
+        } else {
+            Some((local.x + self.size.x * (local.y + self.size.y * local.z)) as usize)
+        }
+    }
+
+    fn get_bit(&self, index: usize) -> bool {
+        self.bits[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    fn set_bit(&mut self, index: usize, value: bool) {
+        if value {
+            self.bits[index / 64] |= 1 << (index % 64);
+        } else {
+            self.bits[index / 64] &= !(1 << (index % 64));
+        }
+    }
+
+    ///Whether cell is inside of volume and nothing occupies it.
+    pub fn is_free(&self, cell: IVec3) -> bool {
+        match self.index(cell) {
+            Some(index) => !self.get_bit(index),
+            None => false,
+        }
+    }
+
+    ///Entity that occupies cell.
+    pub fn owner(&self, cell: IVec3) -> Option<Entity> {
+        self.index(cell).and_then(|index| self.owners[index])
+    }
+
+    ///Entity that occupies cell right above.
+    pub fn on_top(&self, cell: IVec3) -> Option<Entity> {
+        self.owner(cell + IVec3::Y)
+    }
+
+    ///Whether every cell of collider's footprint is free.
+    pub fn is_footprint_free(&self, collider: &Collider, transform: &Transform) -> bool {
+        collider.footprint(transform).all(|cell| self.is_free(cell))
+    }
+
+    ///Whether other entity occupies cell right above any cell of entity's footprint.
+    pub fn carries(&self, entity: Entity, collider: &Collider, transform: &Transform) -> bool {
+        collider
+            .footprint(transform)
+            .any(|cell| self.on_top(cell).is_some_and(|above| above != entity))
+    }
+
+    ///Claims free cells of collider's footprint. Return is how many cells are claimed.
+    pub fn insert(&mut self, entity: Entity, collider: &Collider, transform: &Transform) -> usize {
+        let mut count = 0;
+        for cell in collider.footprint(transform) {
+            if let Some(index) = self.index(cell) {
+                if !self.get_bit(index) {
+                    self.set_bit(index, true);
+                    self.owners[index] = Some(entity);
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    ///Releases cells of collider's footprint that entity owns. Return is how many cells are released.
+    pub fn remove(&mut self, entity: Entity, collider: &Collider, transform: &Transform) -> usize {
+        let mut count = 0;
+        for cell in collider.footprint(transform) {
+            if let Some(index) = self.index(cell) {
+                if self.owners[index] == Some(entity) {
+                    self.set_bit(index, false);
+                    self.owners[index] = None;
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::physics::collider::Shape;
+
+    ///Cells -2 to 2 on every axis.
+    fn grid() -> OccupancyGrid {
+        OccupancyGrid::new(AABB::from_size_offset(5., Vec3::ZERO))
+    }
+
+    fn sphere(radius: f32) -> Collider {
+        Collider::from_shape(Shape::Sphere { radius })
+    }
+
+    #[test]
+    fn bounds_of_volume() {
+        let grid = grid();
+        for cell in [IVec3::splat(-2), IVec3::splat(2), IVec3::new(2, -2, 0)] {
+            assert!(grid.is_free(cell), "{cell}");
+        }
+        for cell in [IVec3::new(3, 0, 0), IVec3::new(0, -3, 0), IVec3::splat(3)] {
+            assert!(!grid.is_free(cell), "{cell}");
+        }
+    }
+
+    #[test]
+    fn footprint_on_cell_edges() {
+        //Bound lies exactly on centers of neighbor cells, which are left out.
+        let footprint = |radius, at| {
+            sphere(radius)
+                .footprint(&Transform::from_translation(at))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(footprint(1., Vec3::ZERO), [IVec3::ZERO]);
+        assert_eq!(footprint(0.5, Vec3::ZERO), [IVec3::ZERO]);
+        assert_eq!(footprint(1.5, Vec3::ZERO).len(), 27);
+        //Half way between cells, bound is on their edges and covers both.
+        assert_eq!(
+            footprint(1., Vec3::new(0.5, 0., 0.)),
+            [IVec3::ZERO, IVec3::X]
+        );
+    }
+
+    #[test]
+    fn insert_and_remove() {
+        let mut grid = grid();
+        let (a, b) = (Entity::from_raw(0), Entity::from_raw(1));
+        let big = sphere(1.5);
+        let at = Transform::from_xyz(2., 0., 0.);
+        //Only cells inside of volume are claimed.
+        assert_eq!(grid.insert(a, &big, &at), 18);
+        assert_eq!(grid.owner(IVec3::new(2, 1, 1)), Some(a));
+        assert!(!grid.is_footprint_free(&sphere(0.5), &Transform::from_xyz(1., 0., 0.)));
+        assert!(grid.is_footprint_free(&sphere(0.5), &Transform::from_xyz(0., 0., 0.)));
+
+        //Cells that are already taken stay with first owner.
+        let small = sphere(0.5);
+        let below = Transform::from_xyz(1., -1., 0.);
+        assert_eq!(grid.insert(b, &small, &below), 0);
+        assert_eq!(grid.on_top(IVec3::new(1, -2, 0)), Some(a));
+        assert_eq!(grid.remove(b, &small, &below), 0);
+        assert_eq!(grid.owner(IVec3::new(1, -1, 0)), Some(a));
+
+        //Only cells right above footprint count as carried.
+        let (c, top) = (Entity::from_raw(2), Transform::from_xyz(-2., 0., 0.));
+        assert!(!grid.carries(a, &big, &at));
+        assert_eq!(grid.insert(c, &small, &top), 1);
+        assert!(grid.carries(b, &small, &Transform::from_xyz(-2., -1., 0.)));
+        assert!(!grid.carries(c, &small, &top));
+
+        assert_eq!(grid.remove(a, &big, &at), 18);
+        assert!(grid.is_free(IVec3::new(2, 1, 1)));
+        assert_eq!(grid.owner(IVec3::new(2, 1, 1)), None);
+    }
+}
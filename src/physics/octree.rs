@@ -302,11 +302,11 @@ impl Octree {
         }
     }
 
-    ///Return hit information about raycast.
-    pub fn raycast(&self, ray: &Ray) -> Option<RayHitInfo> {
+    ///Return hit information about raycast. Entities that filter refuses are passed through.
+    pub fn raycast(&self, ray: &Ray, filter: impl Fn(Entity) -> bool) -> Option<RayHitInfo> {
         let mut len = f32::INFINITY;
         let mut pivot = 0f32;
-        self.raycast_inner(self.root, ray, &mut len, &mut pivot)
+        self.raycast_inner(self.root, ray, &filter, &mut len, &mut pivot)
             .map(|(e, b)| RayHitInfo::new(e, b, len))
     }
 
@@ -314,6 +314,7 @@ impl Octree {
         &self,
         index: usize,
         ray: &Ray,
+        filter: &dyn Fn(Entity) -> bool,
         len: &mut f32,
         pivot: &mut f32,
     ) -> Option<(Entity, AABB)> {
@@ -326,7 +327,7 @@ impl Octree {
                 Some((_, t_max)) => {
                     let mut ret = None;
                     //Raycast entities in node itself.
-                    for entity in node.entities.iter() {
+                    for entity in node.entities.iter().filter(|e| filter(e.entity)) {
                         if let Some(candidate) = entity.aabb.intersects_ray(ray) {
                             if candidate < *len {
                                 ret = Some((entity.entity, entity.aabb));
@@ -351,7 +352,7 @@ impl Octree {
                                     };
                                 } else {
                                     //Get result of raycast on leaf.
-                                    match self.raycast_inner(child_index, ray, len, pivot) {
+                                    match self.raycast_inner(child_index, ray, filter, len, pivot) {
                                         //First success is if and only if the shortest raycast on the leaves.
                                         tmp @ Some(_) => {
                                             ret = tmp;
@@ -24,6 +24,14 @@ impl Ray {
         }
     }
 
+    pub fn origin(&self) -> Vec3 {
+        self.origin
+    }
+
+    pub fn dir(&self) -> Vec3 {
+        self.dir
+    }
+
     pub fn point(&self, t: f32) -> Vec3 {
         self.origin + self.dir * t
     }
@@ -0,0 +1,122 @@
+use crate::physics::{
+    aabb::AABB,
+    collider::Collider,
+    dynamic_tree::{Dynamic, DynamicTree},
+    octree::{Octree, OctreeEntity},
+    ray::{Ray, RayHitInfo},
+};
+
+use bevy::prelude::*;
+
+///Spatial partition split by how often entities move.
+/// - Static entities (placed towers, obstacles) live in Octree and are updated on demand.
+/// - Dynamic entities (enemies, projectiles) live in DynamicTree and are rebuilt every tick.
+/// - Queries search both, so callers don't care where an entity is.
+#[derive(Component)]
+pub struct Spatial {
+    static_tree: Octree,
+    dynamic_tree: DynamicTree,
+}
+
+impl Spatial {
+    pub fn new(static_tree: Octree) -> Self {
+        Self {
+            static_tree,
+            dynamic_tree: DynamicTree::default(),
+        }
+    }
+
+    ///Return is whether entity doesn't already exist.
+    pub fn insert_static(&mut self, entity: OctreeEntity) -> bool {
+        self.static_tree.insert(entity)
+    }
+
+    ///Count of static entities.
+    pub fn static_len(&self) -> usize {
+        self.static_tree.len()
+    }
+
+    ///Return is whether existed entity is removed.
+    pub fn remove_static(&mut self, entity: Entity, aabb: AABB) -> bool {
+        self.static_tree.remove(entity, aabb)
+    }
+
+    ///Iterating entities in both trees that intersects with given bounding box.
+    pub fn _intersect(&self, aabb: AABB, f: impl Fn(&Entity)) {
+        self.static_tree._intersect(aabb, &f);
+        self.dynamic_tree._intersect(aabb, &f);
+    }
+
+    ///Return the nearest hit among both trees.
+    /// - Entities that filter refuses, like debris for placement, are passed through.
+    pub fn raycast(&self, ray: &Ray, filter: impl Fn(Entity) -> bool) -> Option<RayHitInfo> {
+        match (
+            self.static_tree.raycast(ray, &filter),
+            self.dynamic_tree.raycast(ray, &filter),
+        ) {
+            (Some(s), Some(d)) => Some(if d.t < s.t { d } else { s }),
+            (s, d) => s.or(d),
+        }
+    }
+}
+
+///Rebuilds dynamic tree when dynamic entities come or go, otherwise just refits it.
+pub fn update_dynamic_tree(
+    mut spatial: Query<&mut Spatial>,
+    dynamics: Query<(Entity, &Collider, &Transform), With<Dynamic>>,
+    added: Query<(), Added<Dynamic>>,
+    removed: RemovedComponents<Dynamic>,
+) {
+    let rebuild = !added.is_empty() || removed.iter().next().is_some();
+    for mut spatial in spatial.iter_mut() {
+        if rebuild || spatial.dynamic_tree.len() != dynamics.iter().len() {
+            spatial.dynamic_tree.rebuild(
+                dynamics
+                    .iter()
+                    .map(|(entity, collider, transform)| (entity, collider.aabb(transform))),
+            );
+        } else {
+            spatial.dynamic_tree.refit(|entity| {
+                dynamics
+                    .get(entity)
+                    .ok()
+                    .map(|(_, collider, transform)| collider.aabb(transform))
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::physics::collider::Shape;
+
+    #[test]
+    fn raycast_picks_nearest_of_both_trees() {
+        let mut spatial = Spatial::new(Octree::from_size_offset(
+            64,
+            Vec3::splat(0.9),
+            64.,
+            Vec3::ZERO,
+        ));
+        let collider = Collider::from_shape(Shape::Sphere { radius: 0.5 });
+        let (tower, mover) = (Entity::from_raw(0), Entity::from_raw(1));
+        spatial.insert_static(OctreeEntity::new(
+            tower,
+            &collider,
+            &Transform::from_xyz(5., 0., 0.),
+        ));
+        let mover_at = |x| collider.aabb(&Transform::from_xyz(x, 0., 0.));
+        spatial.dynamic_tree.rebuild([(mover, mover_at(10.))]);
+
+        let ray = Ray::new(Vec3::ZERO, Vec3::X);
+        assert_eq!(spatial.raycast(&ray, |_| true).unwrap().entity, tower);
+        spatial.dynamic_tree.refit(|_| Some(mover_at(2.)));
+        assert_eq!(spatial.raycast(&ray, |_| true).unwrap().entity, mover);
+        //Filter applies to both trees.
+        let hit = spatial.raycast(&ray, |entity| entity != mover).unwrap();
+        assert_eq!((hit.entity, hit.t), (tower, 4.5));
+        assert!(spatial.raycast(&ray, |_| false).is_none());
+    }
+}
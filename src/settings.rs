@@ -0,0 +1,234 @@
+use std::{env, fs, io, path::PathBuf};
+
+use bevy::{
+    prelude::*,
+    window::{PresentMode, WindowMode},
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::locale::SOURCE_LANGUAGE;
+
+///Folder in user config directory that game saves to.
+const CONFIG_FOLDER: &str = "game_made_with_rust";
+pub const SETTINGS_FILE: &str = "settings.ron";
+
+///How window covers screen.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum DisplayMode {
+    #[default]
+    Windowed,
+    BorderlessFullscreen,
+    Fullscreen,
+}
+
+impl DisplayMode {
+    const ALL: [DisplayMode; 3] = [
+        DisplayMode::Windowed,
+        DisplayMode::BorderlessFullscreen,
+        DisplayMode::Fullscreen,
+    ];
+
+    ///Mode next to this one, wrapping around.
+    pub fn cycle(self, step: isize) -> Self {
+        let index = Self::ALL.iter().position(|mode| *mode == self).unwrap_or(0) as isize;
+        Self::ALL[(index + step).rem_euclid(Self::ALL.len() as isize) as usize]
+    }
+
+    pub fn window_mode(self) -> WindowMode {
+        match self {
+            DisplayMode::Windowed => WindowMode::Windowed,
+            DisplayMode::BorderlessFullscreen => WindowMode::BorderlessFullscreen,
+            DisplayMode::Fullscreen => WindowMode::Fullscreen,
+        }
+    }
+}
+
+impl std::fmt::Display for DisplayMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DisplayMode::Windowed => write!(f, "Windowed"),
+            DisplayMode::BorderlessFullscreen => write!(f, "Borderless"),
+            DisplayMode::Fullscreen => write!(f, "Fullscreen"),
+        }
+    }
+}
+
+///Options that player can change, saved between runs.
+/// - Fields missing in file keep default, so old files still load.
+#[derive(Resource, Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    ///Degrees camera turns per pixel of mouse motion.
+    pub mouse_sensitivity: f32,
+    ///Degrees per second camera turns with stick fully tilted.
+    pub stick_sensitivity: f32,
+    ///How far stick can tilt before it counts, from 0 to 1.
+    pub stick_dead_zone: f32,
+    ///Units per second camera moves.
+    pub move_speed: f32,
+    ///Seconds to hold button before placing or removing repeats.
+    pub long_press_delay: f32,
+    pub display_mode: DisplayMode,
+    pub vsync: bool,
+    ///Code of ui language.
+    pub language: String,
+    ///Multiplier of ui size, on top of scale factor of screen.
+    pub ui_scale: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            mouse_sensitivity: 0.08,
+            stick_sensitivity: 120.,
+            stick_dead_zone: 0.15,
+            move_speed: 10.,
+            long_press_delay: 1.,
+            display_mode: DisplayMode::Windowed,
+            vsync: true,
+            language: SOURCE_LANGUAGE.to_owned(),
+            ui_scale: 1.,
+        }
+    }
+}
+
+impl Settings {
+    pub fn present_mode(&self) -> PresentMode {
+        match self.vsync {
+            true => PresentMode::AutoVsync,
+            false => PresentMode::AutoNoVsync,
+        }
+    }
+
+    ///Sets up window that is about to be created.
+    pub fn apply_to(&self, window: &mut WindowDescriptor) {
+        window.mode = self.display_mode.window_mode();
+        window.present_mode = self.present_mode();
+    }
+}
+
+///Folder that settings and controls are saved to. None doesn't save, like in headless mode.
+#[derive(Resource, Default)]
+pub struct ConfigDir(pub Option<PathBuf>);
+
+impl ConfigDir {
+    ///Folder of game in user config directory of platform.
+    pub fn user() -> Self {
+        Self(config_dir().map(|dir| dir.join(CONFIG_FOLDER)))
+    }
+
+    pub fn load<T: DeserializeOwned>(&self, file: &str) -> io::Result<T> {
+        let Some(dir) = &self.0 else {
+            return Err(io::ErrorKind::NotFound.into());
+        };
+        ron::from_str(&fs::read_to_string(dir.join(file))?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    ///Loads file that is read before app runs, so there's no logger yet.
+    pub fn load_or_default<T: DeserializeOwned + Default>(&self, file: &str) -> T {
+        match self.load(file) {
+            Ok(value) => value,
+            Err(err) => {
+                if err.kind() != io::ErrorKind::NotFound {
+                    eprintln!("warning: can't load {file}, using defaults: {err}");
+                }
+                T::default()
+            }
+        }
+    }
+
+    pub fn save<T: Serialize>(&self, file: &str, value: &T) {
+        let Some(dir) = &self.0 else {
+            return;
+        };
+        let path = dir.join(file);
+        let result = fs::create_dir_all(dir).and_then(|_| {
+            let text = ron::ser::to_string_pretty(value, default())
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            fs::write(&path, text)
+        });
+        match result {
+            Ok(()) => info!("Saved {}", path.display()),
+            Err(err) => error!("Can't save {}: {err}", path.display()),
+        }
+    }
+}
+
+///Batch setup of settings. Loading is up to app, since window is made from them.
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Settings>()
+            .init_resource::<ConfigDir>()
+            .add_system_to_stage(CoreStage::PostUpdate, apply_settings);
+    }
+}
+
+///Applies changed settings to window. Rest of settings are read where they are used.
+fn apply_settings(settings: Res<Settings>, mut windows: ResMut<Windows>) {
+    if !settings.is_changed() {
+        return;
+    }
+    if let Some(window) = windows.get_primary_mut() {
+        let mode = settings.display_mode.window_mode();
+        if window.mode() != mode {
+            window.set_mode(mode);
+        }
+        let present_mode = settings.present_mode();
+        if window.present_mode() != present_mode {
+            window.set_present_mode(present_mode);
+        }
+    }
+}
+
+///Config directory of platform.
+fn config_dir() -> Option<PathBuf> {
+    let home = || env::var_os("HOME").filter(|home| !home.is_empty());
+    if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home().map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| home().map(|home| PathBuf::from(home).join(".config")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_and_load() {
+        let dir = env::temp_dir().join(format!("{CONFIG_FOLDER}_{}", std::process::id()));
+        let config = ConfigDir(Some(dir.clone()));
+        let settings = Settings {
+            move_speed: 12.,
+            display_mode: DisplayMode::Fullscreen,
+            ..default()
+        };
+        config.save(SETTINGS_FILE, &settings);
+        assert_eq!(config.load::<Settings>(SETTINGS_FILE).unwrap(), settings);
+        fs::remove_dir_all(dir).unwrap();
+        assert_eq!(
+            config.load::<Settings>(SETTINGS_FILE).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+
+        //Missing fields keep default.
+        let partial: Settings = ron::from_str("(vsync: false)").unwrap();
+        assert!(!partial.vsync);
+        assert_eq!(partial.move_speed, Settings::default().move_speed);
+    }
+
+    #[test]
+    fn display_mode_wraps() {
+        assert_eq!(DisplayMode::Windowed.cycle(-1), DisplayMode::Fullscreen);
+        assert_eq!(DisplayMode::Fullscreen.cycle(1), DisplayMode::Windowed);
+    }
+}
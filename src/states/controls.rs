@@ -0,0 +1,350 @@
+use crate::{
+    asset::Fonts,
+    controls::{Binding, InputAction, InputMap, Slot, CONTROLS_FILE},
+    func::*,
+    locale::Locale,
+    settings::ConfigDir,
+    states::*,
+    theme::{TextColor, Theme},
+    ui::*,
+};
+
+use bevy::{input::mouse::MouseWheel, prelude::*, window::WindowCloseRequested};
+
+///Controls screen is same whether it is opened from main menu or pause.
+const CONTROLS_STATES: [AppState; 2] = [
+    AppState::MainMenu(Some(MainMenuState::Controls)),
+    AppState::InGame(Some(InGameState::Controls)),
+];
+
+///Batch setup for controls screen, which is stacked on settings.
+/// - Each action has two keyboard and mouse slots and two gamepad slots.
+/// - Clicking slot waits for next input of its device and puts it there. Back cancels waiting
+///   and Delete empties slot.
+/// - Bindings shared by actions that act at once are reported, but allowed.
+/// - Bindings are saved when screen is left.
+pub struct ControlsMenuPlugin;
+
+impl Plugin for ControlsMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Listening>();
+        for state in CONTROLS_STATES {
+            app.add_system_set_to_stage(CoreStage::PreUpdate, on_enter(state).with_system(setup))
+                .add_system_set_to_stage(
+                    CoreStage::Update,
+                    on_update(state)
+                        .with_system(back_requested)
+                        .with_system(binding_text)
+                        .with_system(status_text),
+                )
+                //After buttons were clicked, so input that is bound doesn't go back or click
+                //other buttons, and input that starts waiting isn't bound.
+                .add_system_set_to_stage(
+                    CoreStage::PostUpdate,
+                    on_update(state)
+                        .with_system(listen)
+                        .with_system(disable_buttons.after(listen))
+                        .with_system(
+                            navigate::<ControlsButton>
+                                .label(NavigationSystem)
+                                .after(listen),
+                        ),
+                )
+                .add_system_set_to_stage(CoreStage::PreUpdate, on_pause(state).with_system(save))
+                .add_system_set_to_stage(CoreStage::PreUpdate, on_exit(state).with_system(save));
+        }
+    }
+}
+
+///Slot of action that waits for input to bind.
+#[derive(Resource, Default)]
+struct Listening(Option<(InputAction, Slot)>);
+
+///Button of controls screen, as its depth differs between main menu and pause.
+#[derive(Component)]
+pub struct ControlsButton;
+
+///Button that shows and rebinds slot of action.
+#[derive(Component)]
+struct BindingButton(InputAction, Slot);
+
+///Text below bindings that tells how to leave waiting, or which bindings conflict.
+#[derive(Component)]
+struct StatusText;
+
+///Binding button action that waits for input.
+fn wait_for(action: InputAction, slot: Slot) -> Action {
+    Action::new(move |mut listening: ResMut<Listening>| listening.0 = Some((action, slot)))
+}
+
+///Setup controls popup.
+fn setup(
+    mut commands: Commands,
+    state: Res<GlobalState>,
+    fonts: Res<Fonts>,
+    theme: Res<Theme>,
+    map: Res<InputMap>,
+    mut listening: ResMut<Listening>,
+) {
+    listening.0 = None;
+    let mut popup = create_popup(&theme, 90.0);
+    popup.0.style.min_size.width = Val::Px(720.0);
+    commands
+        .spawn((popup, state.mark()))
+        .with_children(|parent| {
+            parent.spawn(create_text(
+                CONTROLS_TEXT,
+                &fonts,
+                &theme,
+                40.0,
+                TextColor::Dark,
+            ));
+            for action in InputAction::ALL {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            size: Size::new(Val::Percent(90.0), Val::Px(28.0)),
+                            justify_content: JustifyContent::SpaceBetween,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(create_text(
+                            action.name(),
+                            &fonts,
+                            &theme,
+                            18.0,
+                            TextColor::Dark,
+                        ));
+                        parent.spawn(NodeBundle::default()).with_children(|parent| {
+                            for slot in Slot::ALL {
+                                parent
+                                    .spawn((
+                                        ButtonBundle {
+                                            style: Style {
+                                                size: Size::new(Val::Px(110.0), Val::Px(26.0)),
+                                                margin: UiRect::horizontal(Val::Px(2.0)),
+                                                ..create_button(&theme).style
+                                            },
+                                            ..create_button(&theme)
+                                        },
+                                        BindingButton(action, slot),
+                                        wait_for(action, slot),
+                                        ControlsButton,
+                                    ))
+                                    .with_children(|parent| {
+                                        parent.spawn(create_value_text(
+                                            slot_text(&map, action, slot),
+                                            &fonts,
+                                            &theme,
+                                            16.0,
+                                            TextColor::Bright,
+                                        ));
+                                    });
+                            }
+                        });
+                    });
+            }
+            parent.spawn((
+                create_value_text("", &fonts, &theme, 18.0, TextColor::Dark),
+                StatusText,
+            ));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(100.0), Val::Px(80.0)),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent
+                        .spawn((
+                            create_button(&theme),
+                            Action::new(|mut map: ResMut<InputMap>| {
+                                *map = InputMap::default();
+                            }),
+                            ControlsButton,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn(create_text(
+                                RESET_TEXT,
+                                &fonts,
+                                &theme,
+                                30.0,
+                                TextColor::Bright,
+                            ));
+                        });
+                    parent
+                        .spawn((
+                            create_button(&theme),
+                            Action::new(|mut g: ResMut<GlobalState>| {
+                                let _ = g.back();
+                            }),
+                            ControlsButton,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn(create_text(
+                                BACK_TEXT,
+                                &fonts,
+                                &theme,
+                                30.0,
+                                TextColor::Bright,
+                            ));
+                        });
+                });
+        });
+}
+
+fn slot_text(map: &InputMap, action: InputAction, slot: Slot) -> String {
+    map.binding(action, slot)
+        .map_or_else(|| "-".to_owned(), |binding| binding.to_string())
+}
+
+///Go back unless waiting for input, or to exit state when window is about to close.
+fn back_requested(
+    closed: EventReader<WindowCloseRequested>,
+    mut state: ResMut<GlobalState>,
+    actions: Res<Input<InputAction>>,
+    listening: Res<Listening>,
+) {
+    if !closed.is_empty() {
+        let _ = state.push_exit();
+    } else if listening.0.is_none() && actions.any_just_pressed(BACK_ACTIONS) {
+        let _ = state.back();
+    }
+}
+
+///Binds first input of device of slot that comes while waiting.
+/// - Escape and back actions cancel instead of being bound, so back can't be lost by mistake.
+/// - Delete empties slot.
+fn listen(
+    mut listening: ResMut<Listening>,
+    mut map: ResMut<InputMap>,
+    mut actions: ResMut<Input<InputAction>>,
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<MouseButton>>,
+    pad_buttons: Res<Input<GamepadButton>>,
+    mut wheel: EventReader<MouseWheel>,
+) {
+    let scroll = wheel.iter().map(|w| w.y).sum::<f32>();
+    //Input that started waiting on this frame.
+    if listening.is_changed() {
+        return;
+    }
+    let Some((action, slot)) = listening.0 else {
+        return;
+    };
+    if keys.just_pressed(KeyCode::Escape) || actions.any_just_pressed(BACK_ACTIONS) {
+        debug!("Rebinding {action:?} is canceled");
+    } else if keys.just_pressed(KeyCode::Delete) {
+        map.unbind(action, slot);
+    } else {
+        let binding = if slot.gamepad {
+            pad_buttons
+                .get_just_pressed()
+                .next()
+                .map(|button| Binding::Gamepad(button.button_type))
+        } else if let Some(&key) = keys.get_just_pressed().next() {
+            Some(Binding::Key(key))
+        } else if let Some(&button) = buttons.get_just_pressed().next() {
+            Some(Binding::Mouse(button))
+        } else if scroll > 0. {
+            Some(Binding::WheelUp)
+        } else if scroll < 0. {
+            Some(Binding::WheelDown)
+        } else {
+            None
+        };
+        let Some(binding) = binding else {
+            return;
+        };
+        map.rebind(action, slot, binding);
+    }
+    listening.0 = None;
+    //Input that got bound doesn't also act in menu.
+    actions.clear();
+}
+
+///Buttons can't be clicked while waiting, as click could be what gets bound.
+fn disable_buttons(
+    mut commands: Commands,
+    listening: Res<Listening>,
+    buttons: Query<Entity, (With<Button>, With<ControlsButton>)>,
+) {
+    if !listening.is_changed() {
+        return;
+    }
+    for entity in buttons.iter() {
+        match listening.0 {
+            Some(_) => commands.entity(entity).insert(Disabled),
+            None => commands.entity(entity).remove::<Disabled>(),
+        };
+    }
+}
+
+///Keeps binding texts up to date.
+fn binding_text(
+    map: Res<InputMap>,
+    listening: Res<Listening>,
+    locale: Res<Locale>,
+    buttons: Query<(&BindingButton, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    if !map.is_changed() && !listening.is_changed() && !locale.is_changed() {
+        return;
+    }
+    for (BindingButton(action, slot), children) in buttons.iter() {
+        let value = match listening.0 {
+            Some(listened) if listened == (*action, *slot) => "...".to_owned(),
+            _ => slot_text(&map, *action, *slot),
+        };
+        for child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                text.sections[0].value = value.clone();
+            }
+        }
+    }
+}
+
+///Tells how to leave waiting, or else lists bindings that conflict.
+fn status_text(
+    map: Res<InputMap>,
+    listening: Res<Listening>,
+    locale: Res<Locale>,
+    mut texts: Query<&mut Text, With<StatusText>>,
+) {
+    if !map.is_changed() && !listening.is_changed() && !locale.is_changed() {
+        return;
+    }
+    let value = match listening.0 {
+        Some((action, _)) => format!(
+            "{}: {}. {}",
+            locale.get(PRESS_ANY_TEXT),
+            locale.get(action.name()),
+            locale.get(LISTEN_HINT_TEXT)
+        ),
+        None => map
+            .conflicts()
+            .into_iter()
+            .map(|(binding, a, b)| {
+                format!(
+                    "{}: {binding} ({}, {})",
+                    locale.get(BOUND_TWICE_TEXT),
+                    locale.get(a.name()),
+                    locale.get(b.name())
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    };
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = value.clone();
+    }
+}
+
+fn save(map: Res<InputMap>, config: Res<ConfigDir>) {
+    config.save(CONTROLS_FILE, &*map);
+}
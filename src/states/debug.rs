@@ -0,0 +1,84 @@
+use crate::{
+    asset::{Fonts, FONT_SCHLUBER},
+    states::*,
+};
+
+use bevy::prelude::*;
+
+///Key that toggles state debug view.
+const TOGGLE_KEY: KeyCode = KeyCode::F3;
+
+///Batch setup of state debug view.
+pub struct StateDebugPlugin;
+
+impl Plugin for StateDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(CoreStage::Update, toggle_debug_view)
+            .add_system_to_stage(CoreStage::PostUpdate, update_debug_view);
+    }
+}
+
+///Mark text listing state history. Lives through every state.
+#[derive(Component)]
+struct StateDebugText;
+
+///Spawns view on first toggle, since font isn't ready on startup.
+fn toggle_debug_view(
+    mut commands: Commands,
+    mut view: Query<&mut Visibility, With<StateDebugText>>,
+    fonts: Res<Fonts>,
+    input: Res<Input<KeyCode>>,
+) {
+    if !input.just_pressed(TOGGLE_KEY) {
+        return;
+    }
+    match view.get_single_mut() {
+        Ok(mut visibility) => visibility.is_visible = !visibility.is_visible,
+        Err(_) => {
+            commands.spawn((
+                TextBundle {
+                    z_index: ZIndex::Global(i32::MAX - 1),
+                    ..TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font: fonts[FONT_SCHLUBER].clone(),
+                            font_size: 16.0,
+                            color: Color::LIME_GREEN,
+                        },
+                    )
+                    .with_style(Style {
+                        position_type: PositionType::Absolute,
+                        position: UiRect {
+                            left: Val::Px(8.0),
+                            top: Val::Px(8.0),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                },
+                BackgroundColor(Color::rgba(0., 0., 0., 0.6)),
+                StateDebugText,
+            ));
+        }
+    }
+}
+
+///Lists current stack and history with elapsed seconds, the newest first.
+fn update_debug_view(
+    mut view: Query<(&mut Text, &Visibility), With<StateDebugText>>,
+    state: Res<GlobalState>,
+) {
+    for (mut text, visibility) in view.iter_mut() {
+        if !visibility.is_visible {
+            continue;
+        }
+        let mut value = format!("Current: {:?}\n", state.current());
+        for (elapsed, transition) in state.history().rev() {
+            value += &format!(
+                "{elapsed:>8.2}s {:?} {:?} -> {:?}\n",
+                transition.way, transition.from, transition.to
+            );
+        }
+        text.sections[0].value = value;
+    }
+}
@@ -0,0 +1,168 @@
+use crate::controls::ActionSystem;
+
+use bevy::{
+    input::{
+        mouse::{MouseMotion, MouseWheel},
+        InputSystem,
+    },
+    prelude::*,
+    ui::{FocusPolicy, UiSystem},
+};
+
+///How screen fades when state changes. Durations are in seconds.
+#[derive(Clone, Copy, Debug)]
+pub struct Fade {
+    pub fade_out: f32,
+    pub fade_in: f32,
+    pub color: Color,
+}
+
+impl Fade {
+    pub const fn new(fade_out: f32, fade_in: f32) -> Self {
+        Self {
+            fade_out,
+            fade_in,
+            color: Color::BLACK,
+        }
+    }
+}
+
+impl Default for Fade {
+    fn default() -> Self {
+        Self::new(0.4, 0.4)
+    }
+}
+
+enum FadePhase {
+    Idle,
+    ///Covering screen. State changes after it.
+    Out(Timer),
+    ///Revealing screen of new state.
+    In(Timer),
+}
+
+///Progress of screen fade. State change waits while fading out.
+#[derive(Resource)]
+pub struct ScreenFade {
+    phase: FadePhase,
+    fade: Fade,
+}
+
+impl Default for ScreenFade {
+    fn default() -> Self {
+        Self {
+            phase: FadePhase::Idle,
+            fade: Fade::default(),
+        }
+    }
+}
+
+impl ScreenFade {
+    pub fn start(&mut self, fade: Fade) {
+        self.fade = fade;
+        self.phase = FadePhase::Out(Timer::from_seconds(fade.fade_out, TimerMode::Once));
+    }
+
+    pub fn is_fading_out(&self) -> bool {
+        matches!(self.phase, FadePhase::Out(_))
+    }
+
+    pub fn is_running(&self) -> bool {
+        !matches!(self.phase, FadePhase::Idle)
+    }
+}
+
+///Mark node that covers screen while fading.
+#[derive(Component)]
+pub struct FadeOverlay;
+
+///Batch setup of screen fade.
+pub struct FadePlugin;
+
+impl Plugin for FadePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ScreenFade>()
+            .add_startup_system(setup_fade)
+            //Input is taken before anything else reads it.
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                fade_screen
+                    .after(InputSystem)
+                    .before(ActionSystem)
+                    .before(UiSystem::Focus),
+            );
+    }
+}
+
+///Spawns overlay that lives through every state.
+fn setup_fade(mut commands: Commands) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                ..default()
+            },
+            background_color: BackgroundColor(Color::NONE),
+            focus_policy: FocusPolicy::Block,
+            visibility: Visibility::INVISIBLE,
+            z_index: ZIndex::Global(i32::MAX),
+            ..default()
+        },
+        FadeOverlay,
+    ));
+}
+
+///Animates overlay, blocks input while running and skips on any key or click.
+fn fade_screen(
+    mut screen_fade: ResMut<ScreenFade>,
+    mut overlay: Query<(&mut BackgroundColor, &mut Visibility), With<FadeOverlay>>,
+    mut keys: ResMut<Input<KeyCode>>,
+    mut buttons: ResMut<Input<MouseButton>>,
+    mut pad_buttons: ResMut<Input<GamepadButton>>,
+    mut motion: ResMut<Events<MouseMotion>>,
+    mut wheel: ResMut<Events<MouseWheel>>,
+    time: Res<Time>,
+) {
+    if !screen_fade.is_running() {
+        return;
+    }
+    let skip = keys.get_just_pressed().next().is_some()
+        || buttons.get_just_pressed().next().is_some()
+        || pad_buttons.get_just_pressed().next().is_some();
+    keys.reset_all();
+    buttons.reset_all();
+    pad_buttons.reset_all();
+    motion.clear();
+    wheel.clear();
+
+    let fade = screen_fade.fade;
+    let alpha = match &mut screen_fade.phase {
+        FadePhase::Out(timer) => {
+            timer.tick(time.delta());
+            if skip || timer.finished() {
+                screen_fade.phase =
+                    FadePhase::In(Timer::from_seconds(fade.fade_in, TimerMode::Once));
+                1.
+            } else {
+                timer.percent()
+            }
+        }
+        FadePhase::In(timer) => {
+            timer.tick(time.delta());
+            if skip || timer.finished() {
+                screen_fade.phase = FadePhase::Idle;
+                0.
+            } else {
+                timer.percent_left()
+            }
+        }
+        FadePhase::Idle => 0.,
+    };
+    let mut faded = fade.color;
+    faded.set_a(fade.color.a() * alpha);
+    for (mut color, mut visibility) in overlay.iter_mut() {
+        color.0 = faded;
+        visibility.is_visible = screen_fade.is_running();
+    }
+}
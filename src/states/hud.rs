@@ -0,0 +1,283 @@
+use crate::{
+    asset::Fonts,
+    layout::{Anchor, Anchored},
+    locale::Locale,
+    physics::spatial::Spatial,
+    states::{
+        in_game::{Building, LookAt, Money, Obstacle, Wave},
+        palette::Palette,
+        *,
+    },
+    theme::{TextColor, Theme},
+    ui::*,
+};
+
+use bevy::prelude::*;
+
+///Seconds notification stays on screen.
+const NOTIFICATION_TIME: f32 = 3.;
+
+///Message for player that shows up for a while. English text, translated when shown.
+pub struct Notification(pub &'static str);
+
+///Root node of every widget, hidden while game is paused.
+#[derive(Component)]
+struct Hud;
+
+///Text that shows money.
+#[derive(Component)]
+struct MoneyWidget;
+
+///Text that shows wave number and time until next one.
+#[derive(Component)]
+struct WaveWidget;
+
+///Text that shows what is placed next.
+#[derive(Component)]
+struct SelectedWidget;
+
+///Text that shows building camera looks at.
+#[derive(Component)]
+struct LookAtWidget;
+
+///Text that shows how many towers are built.
+#[derive(Component)]
+struct TowerCountWidget;
+
+///Node that lists notifications.
+#[derive(Component)]
+struct NotificationWidget;
+
+///Notification in list, until its time is up.
+#[derive(Component)]
+struct Notice {
+    text: &'static str,
+    timer: Timer,
+}
+
+///Batch setup of in game HUD.
+/// - Each widget is its own text with own marker and system, so one can be added or removed
+///   without touching others.
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Notification>()
+            .add_system_set_to_stage(
+                CoreStage::PostUpdate,
+                on_enter(AppState::InGame(None)).with_system(setup),
+            )
+            .add_system_set_to_stage(
+                CoreStage::PostUpdate,
+                on_update(AppState::InGame(None))
+                    .with_system(money_widget)
+                    .with_system(wave_widget)
+                    .with_system(selected_widget)
+                    .with_system(look_at_widget)
+                    .with_system(tower_count_widget)
+                    .with_system(notification_widget),
+            )
+            .add_system_set_to_stage(
+                CoreStage::PostUpdate,
+                on_pause(AppState::InGame(None)).with_system(hide),
+            )
+            .add_system_set_to_stage(
+                CoreStage::PostUpdate,
+                on_resume(AppState::InGame(None)).with_system(show),
+            );
+    }
+}
+
+///Spawns every widget. They get text on next frame.
+fn setup(mut commands: Commands, state: Res<GlobalState>, fonts: Res<Fonts>, theme: Res<Theme>) {
+    let text = |size| create_value_text("", &fonts, &theme, size, TextColor::Bright);
+    let anchored = |size, anchor, x, y| {
+        (
+            text(size),
+            Anchored::new(anchor, Vec2::new(x, y)),
+            Hud,
+            state.mark(),
+        )
+    };
+    commands.spawn((anchored(24.0, Anchor::TopLeft, 12.0, 12.0), MoneyWidget));
+    commands.spawn((anchored(20.0, Anchor::TopLeft, 12.0, 44.0), WaveWidget));
+    commands.spawn((
+        anchored(20.0, Anchor::TopRight, -12.0, 12.0),
+        TowerCountWidget,
+    ));
+    commands.spawn((
+        anchored(20.0, Anchor::BottomLeft, 12.0, -12.0),
+        SelectedWidget,
+    ));
+    //Below crosshair
+    commands.spawn((anchored(20.0, Anchor::Center, 0.0, 48.0), LookAtWidget));
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        },
+        Anchored::new(Anchor::Top, Vec2::new(0.0, 12.0)),
+        NotificationWidget,
+        Hud,
+        state.mark(),
+    ));
+}
+
+///Keeps screens above game clear.
+fn hide(mut huds: Query<&mut Visibility, With<Hud>>) {
+    for mut visibility in huds.iter_mut() {
+        visibility.is_visible = false;
+    }
+}
+
+///Brings HUD back when game is on top again.
+fn show(mut huds: Query<&mut Visibility, With<Hud>>) {
+    for mut visibility in huds.iter_mut() {
+        visibility.is_visible = true;
+    }
+}
+
+///Sets text only when it differs, so change detection stays quiet.
+fn set_text(mut text: Mut<Text>, value: String) {
+    if text.sections[0].value != value {
+        text.sections[0].value = value;
+    }
+}
+
+fn money_widget(
+    money: Res<Money>,
+    locale: Res<Locale>,
+    mut texts: Query<&mut Text, With<MoneyWidget>>,
+) {
+    for text in texts.iter_mut() {
+        set_text(text, format!("{}: {}", locale.get(MONEY_TEXT), money.0));
+    }
+}
+
+fn wave_widget(
+    wave: Res<Wave>,
+    locale: Res<Locale>,
+    mut texts: Query<&mut Text, With<WaveWidget>>,
+) {
+    let remaining = wave.timer.remaining_secs().ceil();
+    for text in texts.iter_mut() {
+        set_text(
+            text,
+            format!(
+                "{}: {}   {}: {remaining}s",
+                locale.get(WAVE_TEXT),
+                wave.number,
+                locale.get(NEXT_WAVE_TEXT)
+            ),
+        );
+    }
+}
+
+fn selected_widget(
+    palette: Res<Palette>,
+    locale: Res<Locale>,
+    mut texts: Query<&mut Text, With<SelectedWidget>>,
+) {
+    let item = palette.selected();
+    for text in texts.iter_mut() {
+        set_text(
+            text,
+            format!(
+                "{}   {}: {}",
+                locale.get(item.name),
+                locale.get(COST_TEXT),
+                item.cost
+            ),
+        );
+    }
+}
+
+///Name and stats of building, and nothing while camera looks elsewhere.
+fn look_at_widget(
+    camera: Query<&LookAt, With<Camera>>,
+    buildings: Query<(&Building, &Transform)>,
+    locale: Res<Locale>,
+    mut texts: Query<&mut Text, With<LookAtWidget>>,
+) {
+    let building = camera
+        .iter()
+        .find_map(|look_at| look_at.0.as_ref())
+        .and_then(|hit| buildings.get(hit.entity).ok());
+    let value = match building {
+        Some((building, transform)) => {
+            let cell = transform.translation.round().as_ivec3();
+            format!(
+                "{}\n{}: {}   {} {} {}",
+                locale.get(building.name),
+                locale.get(COST_TEXT),
+                building.cost,
+                cell.x,
+                cell.y,
+                cell.z
+            )
+        }
+        None => String::new(),
+    };
+    for text in texts.iter_mut() {
+        set_text(text, value.clone());
+    }
+}
+
+///Counts towers in static tree, which also has obstacles of map.
+fn tower_count_widget(
+    spatial: Query<&Spatial>,
+    obstacles: Query<(), With<Obstacle>>,
+    locale: Res<Locale>,
+    mut texts: Query<&mut Text, With<TowerCountWidget>>,
+) {
+    let Ok(spatial) = spatial.get_single() else {
+        return;
+    };
+    let count = spatial
+        .static_len()
+        .saturating_sub(obstacles.iter().count());
+    for text in texts.iter_mut() {
+        set_text(text, format!("{}: {count}", locale.get(TOWERS_TEXT)));
+    }
+}
+
+///Lists new notifications and removes old ones. Same one isn't listed twice at once.
+fn notification_widget(
+    mut commands: Commands,
+    mut events: EventReader<Notification>,
+    mut notices: Query<(Entity, &mut Notice)>,
+    lists: Query<Entity, With<NotificationWidget>>,
+    fonts: Res<Fonts>,
+    theme: Res<Theme>,
+    time: Res<Time>,
+) {
+    let mut shown = Vec::new();
+    for (entity, mut notice) in notices.iter_mut() {
+        if notice.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        } else {
+            shown.push(notice.text);
+        }
+    }
+    for Notification(text) in events.iter() {
+        if shown.contains(text) {
+            continue;
+        }
+        shown.push(text);
+        for list in lists.iter() {
+            commands.entity(list).with_children(|parent| {
+                parent.spawn((
+                    create_text(text, &fonts, &theme, 24.0, TextColor::Bright),
+                    Notice {
+                        text,
+                        timer: Timer::from_seconds(NOTIFICATION_TIME, TimerMode::Once),
+                    },
+                ));
+            });
+        }
+    }
+}
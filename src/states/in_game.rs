@@ -1,22 +1,49 @@
 use crate::{
     asset::*,
+    blueprint::Blueprint,
     consts::*,
-    physics::{aabb::AABB, octree::Octree, ray::Ray},
-    states::*,
-    ui::*,
+    controls::{ActionSystem, InputAction, Sticks},
+    layout::{Anchor, Anchored},
+    physics::{
+        aabb::AABB,
+        body::{integrate_bodies, Velocity},
+        collider::{Collider, Shape},
+        dynamic_tree::Dynamic,
+        occupancy::OccupancyGrid,
+        octree::{Octree, OctreeEntity},
+        ray::{Ray, RayHitInfo},
+        spatial::Spatial,
+    },
+    settings::Settings,
+    states::{
+        hud::Notification,
+        palette::{Buildable, Palette},
+        pause::pause_requested,
+        *,
+    },
+    terrain::{
+        generate::{MapGenerator, MapSeed},
+        Terrain, Voxel,
+    },
+    ui::WAVE_STARTED_TEXT,
 };
 
-use bevy::input::mouse::MouseWheel;
 use bevy::{input::mouse::MouseMotion, prelude::*, window::CursorGrabMode};
 
-use crate::physics::collider::{Collider, Shape};
-use crate::physics::octree::OctreeEntity;
-use crate::physics::ray::RayHitInfo;
 use bevy_polyline::prelude::*;
 
 const BLUEPRINT_BOUND: AABB =
     unsafe { AABB::new_unchecked(Vec3::new(-31.5, -0.5, -31.5), Vec3::new(31.5, 62.5, 31.5)) };
 
+///Seconds before debris of destroyed tower disappears.
+const DEBRIS_LIFETIME: f32 = 5.;
+
+///Money player starts game with. Only shown, as nothing earns or spends money yet.
+const START_MONEY: u32 = 500;
+
+///Seconds between waves.
+const WAVE_INTERVAL: f32 = 30.;
+
 ///Batch setup for In game.
 pub struct InGamePlugin;
 
@@ -24,25 +51,39 @@ impl Plugin for InGamePlugin {
     fn build(&self, app: &mut App) {
         app.add_system_set_to_stage(
             CoreStage::PreUpdate,
-            SystemSet::on_enter(PreUpdateStageState::InGame).with_system(setup),
+            on_enter(AppState::InGame(None)).with_system(setup),
         )
         .add_system_set_to_stage(
             CoreStage::PreUpdate,
-            SystemSet::on_update(PreUpdateStageState::InGame)
+            on_update(AppState::InGame(None))
                 .with_system(grab_cursor)
-                .with_system(camera_look_at),
+                .with_system(camera_look_at.after(ActionSystem)),
         )
         .add_system_set_to_stage(
             CoreStage::PreUpdate,
-            SystemSet::on_pause(PreUpdateStageState::InGame).with_system(show_cursor),
+            on_pause(AppState::InGame(None)).with_system(show_cursor),
         )
+        .add_system_set_to_stage(
+            CoreStage::PreUpdate,
+            on_exit(AppState::InGame(None)).with_system(show_cursor),
+        )
+        //Click or key that resumed shouldn't leak into game.
+        .add_state_callback(AppState::InGame(None), StateHook::Resume, |world, _| {
+            world.resource_mut::<Input<MouseButton>>().reset_all();
+            world.resource_mut::<Input<KeyCode>>().reset_all();
+            world.resource_mut::<Input<GamepadButton>>().reset_all();
+            world.resource_mut::<Input<InputAction>>().reset_all();
+        })
         .add_system_set_to_stage(
             CoreStage::Update,
-            SystemSet::on_update(UpdateStageState::InGame)
+            on_update(AppState::InGame(None))
                 .with_system(move_camera)
                 .with_system(place)
                 .with_system(replace)
-                .with_system(close_requested),
+                .with_system(integrate_bodies)
+                .with_system(despawn_debris)
+                .with_system(advance_wave)
+                .with_system(pause_requested),
         );
     }
 }
@@ -56,7 +97,8 @@ fn setup(
     standard_materials: Res<StandardMaterials>,
     polylines: Res<Polylines>,
     polyline_materials: Res<PolylineMaterials>,
-    windows: Res<Windows>,
+    map_seed: Res<MapSeed>,
+    blueprint: Option<Res<Blueprint>>,
 ) {
     //camera
     commands.spawn((
@@ -68,23 +110,16 @@ fn setup(
         LookAt(None),
     ));
     //crosshair
-    let window = windows.primary();
     commands.spawn((
         ImageBundle {
             image: textures[IMAGE_UI][CROSSHAIR].clone().into(),
             style: Style {
                 size: Size::new(Val::Px(32.), Val::Px(32.)),
-                position_type: PositionType::Absolute,
-                position: UiRect::new(
-                    Val::Px(window.width() * 0.5 - 16.),
-                    Val::Undefined,
-                    Val::Undefined,
-                    Val::Px(window.height() * 0.5 - 16.),
-                ),
                 ..default()
             },
             ..default()
         },
+        Anchored::new(Anchor::Center, Vec2::ZERO),
         state.mark(),
     ));
     //directional light
@@ -102,17 +137,6 @@ fn setup(
         },
         state.mark(),
     ));
-    //plane
-    commands.spawn((
-        PbrBundle {
-            mesh: meshs[MESH_BUILT_IN][PLANE].clone(),
-            material: standard_materials[S_MAT_BUILT_IN][SEA_GREEN].clone(),
-            transform: Transform::from_scale(Vec3::new(100., 1., 100.))
-                .with_translation(Vec3::new(0., -0.5, 0.)),
-            ..default()
-        },
-        state.mark(),
-    ));
     //x axis line
     commands.spawn((
         PolylineBundle {
@@ -145,25 +169,106 @@ fn setup(
         },
         state.mark(),
     ));
-    //Octree
-    commands.spawn((
-        Octree::from_size_offset(64, Vec3::splat(0.9), 64., Vec3::new(0.5, 31.5, 0.5)),
-        state.mark(),
+    //terrain
+    let mut terrain = Terrain::new(
+        BLUEPRINT_BOUND,
+        standard_materials[S_MAT_BUILT_IN][WHITE].clone(),
+    );
+    let mut spatial = Spatial::new(Octree::from_size_offset(
+        64,
+        Vec3::splat(0.9),
+        64.,
+        Vec3::new(0.5, 31.5, 0.5),
     ));
-    //selection
+    let mut grid = OccupancyGrid::new(BLUEPRINT_BOUND);
+    match map_seed.0 {
+        Some(seed) => {
+            let layout = MapGenerator::new(seed).generate(&mut terrain);
+            //spawn and goal markers
+            for (cell, material) in [(layout.spawn, RED), (layout.goal, BLUE)] {
+                commands.spawn((
+                    PolylineBundle {
+                        polyline: polylines[UNIT_X].clone(),
+                        material: polyline_materials[material].clone(),
+                        transform: Transform::from_translation(cell.as_vec3())
+                            .with_rotation(Quat::from_rotation_z(FRAC_PI_2))
+                            .with_scale(Vec3::new(8., 1., 1.)),
+                        ..default()
+                    },
+                    state.mark(),
+                ));
+            }
+            //rocks
+            for (center, radius) in layout.rocks {
+                let transform = Transform::from_translation(center).with_scale(Vec3::splat(radius));
+                let collider = Collider::from_shape(Shape::Sphere { radius });
+                let entity = commands
+                    .spawn((
+                        PbrBundle {
+                            mesh: meshs[MESH_BUILT_IN][SPHERE].clone(),
+                            material: standard_materials[S_MAT_BUILT_IN][ROCK].clone(),
+                            transform,
+                            ..default()
+                        },
+                        collider.clone(),
+                        Obstacle,
+                        state.mark(),
+                    ))
+                    .id();
+                spatial.insert_static(OctreeEntity::new(entity, &collider, &transform));
+                grid.insert(entity, &collider, &transform);
+            }
+        }
+        None => terrain.fill(IVec3::new(-31, 0, -31), IVec3::new(31, 0, 31), Voxel::Grass),
+    }
+    //Terrain goes into spatial tree by bound of each chunk, next to obstacles on it.
+    for aabb in terrain.chunk_bounds() {
+        let collider = Collider::from_shape(Shape::Cuboid {
+            center: aabb.center(),
+            half_size: aabb.length() * 0.5,
+        });
+        let entity = commands
+            .spawn((
+                TransformBundle::default(),
+                collider.clone(),
+                Ground,
+                Obstacle,
+                state.mark(),
+            ))
+            .id();
+        spatial.insert_static(OctreeEntity::new(entity, &collider, &Transform::IDENTITY));
+    }
+    //money and waves, which HUD shows
+    commands.insert_resource(Money(START_MONEY));
+    commands.insert_resource(Wave::default());
+    //selection, which starts at first item of palette.
+    let palette = Palette::new(&meshs, &standard_materials);
     let selection = Selection::new(
-        vec![
-            meshs[MESH_WEAPON][GUN_TOWER_0_BASE].clone(),
-            meshs[MESH_WEAPON][GUN_TOWER_0_TOWER].clone(),
-            meshs[MESH_WEAPON][GUN_TOWER_0_GUN].clone(),
-        ],
-        standard_materials[S_MAT_BUILT_IN][WHITE].clone(),
+        palette.selected().clone(),
         standard_materials[S_MAT_BUILT_IN][WHITE_TRANS].clone(),
-        Collider::from_shape(Shape::CutSphere {
-            radius: 2.5,
-            cut: 0.5,
-        }),
     );
+    commands.insert_resource(palette);
+    //towers of blueprint given on launch
+    for &(cell, turns) in blueprint.iter().flat_map(|blueprint| &blueprint.towers) {
+        let transform = Transform::from_translation(cell.as_vec3())
+            .with_rotation(Quat::from_rotation_y(turns as f32 * FRAC_PI_2));
+        let collider = &selection.buildable.collider;
+        if !grid.is_footprint_free(collider, &transform) || terrain.get(cell).is_solid() {
+            warn!("Blueprint tower at {cell} is blocked");
+            continue;
+        }
+        spawn_tower(
+            &mut commands,
+            &selection,
+            transform,
+            &state,
+            &mut spatial,
+            &mut grid,
+        );
+    }
+    commands.spawn((SpatialBundle::default(), terrain, state.mark()));
+    //Spatial partition
+    commands.spawn((spatial, grid, state.mark()));
     let children = selection.create_transparent();
     commands
         .spawn((
@@ -181,7 +286,9 @@ fn setup(
 
 ///locks cursor to window while in game.
 fn grab_cursor(mut windows: ResMut<Windows>) {
-    let window = windows.primary_mut();
+    let Some(window) = windows.get_primary_mut() else {
+        return;
+    };
     let cursor_visible = window.cursor_visible();
     if window.is_focused() {
         //if window is focused and cursor is visible, lock.
@@ -197,28 +304,36 @@ fn grab_cursor(mut windows: ResMut<Windows>) {
     }
 }
 
-///Release cursor when about to exit.
+///Release cursor when about to exit, pause or leave game.
 fn show_cursor(mut windows: ResMut<Windows>) {
-    let window = windows.primary_mut();
-    window.set_cursor_grab_mode(CursorGrabMode::None);
-    window.set_cursor_visibility(true);
+    if let Some(window) = windows.get_primary_mut() {
+        window.set_cursor_grab_mode(CursorGrabMode::None);
+        window.set_cursor_visibility(true);
+    }
 }
 
 ///Camera control system.
 fn move_camera(
     mut query: Query<&mut Transform, With<Camera>>,
-    input: Res<Input<KeyCode>>,
+    actions: Res<Input<InputAction>>,
+    sticks: Res<Sticks>,
     mut mouse: EventReader<MouseMotion>,
     time: Res<Time>,
+    settings: Res<Settings>,
 ) {
     //mouse motion to angular delta.
     let mut motion = Vec2::ZERO;
     if !mouse.is_empty() {
         mouse.iter().for_each(|m| motion += m.delta);
-        motion *= -RADIANS * 0.08;
+        motion *= -RADIANS * settings.mouse_sensitivity;
     }
+    //Stick turns by time rather than by distance, and pushing it up looks up unlike mouse.
+    motion += Vec2::new(-sticks.look.x, sticks.look.y)
+        * RADIANS
+        * settings.stick_sensitivity
+        * time.delta_seconds();
 
-    let delta = time.delta_seconds() * 10.0;
+    let delta = time.delta_seconds() * settings.move_speed;
     for mut transform in query.iter_mut() {
         //camera rotation by mouse motion.
         if motion != Vec2::ZERO {
@@ -230,28 +345,22 @@ fn move_camera(
                 0.0,
             );
         }
-        //Accumulate move direction from keyboard inputs.
+        //Accumulate move direction from keyboard inputs and stick.
         let front = transform.forward();
         let right = transform.right();
         let up = Vec3::Y;
-        let mut to_move = Vec3::ZERO;
-        if input.any_pressed([KeyCode::W, KeyCode::Up]) {
-            to_move += front;
-        }
-        if input.any_pressed([KeyCode::A, KeyCode::Left]) {
-            to_move -= right;
-        }
-        if input.any_pressed([KeyCode::S, KeyCode::Down]) {
-            to_move -= front;
-        }
-        if input.any_pressed([KeyCode::D, KeyCode::Right]) {
-            to_move += right;
-        }
-        if input.pressed(KeyCode::Space) {
-            to_move += up;
-        }
-        if input.pressed(KeyCode::LShift) {
-            to_move -= up;
+        let mut to_move = front * sticks.movement.y + right * sticks.movement.x;
+        for (action, direction) in [
+            (InputAction::MoveForward, front),
+            (InputAction::MoveBack, -front),
+            (InputAction::MoveLeft, -right),
+            (InputAction::MoveRight, right),
+            (InputAction::MoveUp, up),
+            (InputAction::MoveDown, -up),
+        ] {
+            if actions.pressed(action) {
+                to_move += direction;
+            }
         }
         //apply
         transform.translation = (transform.translation + to_move.clamp_length_max(1.0) * delta)
@@ -260,35 +369,74 @@ fn move_camera(
 }
 
 #[derive(Component)]
-pub struct LookAt(Option<RayHitInfo>);
+pub struct LookAt(pub Option<RayHitInfo>);
+
+///Money player has. Placeholder that HUD shows, as nothing earns or spends it yet.
+#[derive(Resource)]
+pub struct Money(pub u32);
+
+///Waves so far, and time until next one.
+/// - There are no enemies yet, so wave only counts up and is told.
+#[derive(Resource)]
+pub struct Wave {
+    pub number: u32,
+    pub timer: Timer,
+}
+
+impl Default for Wave {
+    fn default() -> Self {
+        Self {
+            number: 0,
+            timer: Timer::from_seconds(WAVE_INTERVAL, TimerMode::Repeating),
+        }
+    }
+}
+
+///What placed entity was built as.
+#[derive(Component)]
+pub struct Building {
+    pub name: &'static str,
+    ///Price it is listed with. Nothing charges it yet.
+    pub cost: u32,
+}
+
+///Part of destroyed tower that falls and disappears after a while.
+#[derive(Component)]
+pub struct Debris(Timer);
+
+///Mark entity that is part of map rather than built by player, so it can't be removed.
+#[derive(Component)]
+pub struct Obstacle;
 
+///Bound of solid cells of terrain chunk in spatial tree.
+#[derive(Component)]
+pub struct Ground;
+
+///Ghost that shows what is placed where.
 #[derive(Component)]
 pub struct Selection {
     valid: bool,
-    meshes: Vec<Handle<Mesh>>,
-    material: Handle<StandardMaterial>,
+    buildable: Buildable,
     material_trans: Handle<StandardMaterial>,
-    collider: Collider,
 }
 
 impl Selection {
-    pub fn new(
-        meshes: Vec<Handle<Mesh>>,
-        material: Handle<StandardMaterial>,
-        material_trans: Handle<StandardMaterial>,
-        collider: Collider,
-    ) -> Self {
+    pub fn new(buildable: Buildable, material_trans: Handle<StandardMaterial>) -> Self {
         Self {
             valid: false,
-            meshes,
-            material,
+            buildable,
             material_trans,
-            collider,
         }
     }
 
+    ///Changes what is placed. Ghost meshes have to be respawned from `create_transparent`.
+    pub fn set(&mut self, buildable: Buildable) {
+        self.buildable = buildable;
+    }
+
     pub fn create_transparent(&self) -> Vec<PbrBundle> {
-        self.meshes
+        self.buildable
+            .meshes
             .iter()
             .map(|mesh| PbrBundle {
                 mesh: mesh.clone(),
@@ -299,42 +447,35 @@ impl Selection {
     }
 
     pub fn create(&self) -> Vec<PbrBundle> {
-        self.meshes
+        self.buildable
+            .meshes
             .iter()
             .map(|mesh| PbrBundle {
                 mesh: mesh.clone(),
-                material: self.material.clone(),
+                material: self.buildable.material.clone(),
                 ..default()
             })
             .collect()
     }
 }
 
-fn _select(
-    mut selected: Query<(
-        &mut Handle<Mesh>,
-        &mut Handle<StandardMaterial>,
-        &mut Selection,
-    )>,
-) {
-    let _ = selected.single_mut();
-}
+///What look at ray goes through.
+type PassedByLook = Or<(With<Debris>, With<Ground>)>;
 
 ///Prepare and store data about where camera looking at.
 fn camera_look_at(
     mut camera: Query<(&Transform, &mut LookAt), With<Camera>>,
-    octree: Query<&Octree>,
+    spatial: Query<(&Spatial, &OccupancyGrid)>,
+    terrain: Query<&Terrain>,
     mut selection: Query<(&mut Selection, &mut Transform), Without<Camera>>,
-    mut mouse_wheel: EventReader<MouseWheel>,
+    passed: Query<(), PassedByLook>,
+    actions: Res<Input<InputAction>>,
     mut rotate: Local<i32>,
 ) {
-    let mut accum = 0.;
-    for delta in mouse_wheel.iter() {
-        accum += delta.y;
-    }
-    if accum > 0. {
+    if actions.just_pressed(InputAction::RotateGhost) {
         *rotate += 1
-    } else if accum < 0. {
+    }
+    if actions.just_pressed(InputAction::RotateGhostBack) {
         *rotate -= 1
     }
     let y_rot = (*rotate % 4) as f32 * 90f32.to_radians();
@@ -342,12 +483,17 @@ fn camera_look_at(
     let (camera_transform, mut look_at) = camera.single_mut();
     let camera_pos = camera_transform.translation;
     let camera_forward = camera_transform.forward();
-    let octree = octree.single();
+    let (spatial, grid) = spatial.single();
+    let terrain = terrain.single();
     let (mut selection, mut transform) = selection.single_mut();
     //Get raycast hit point.
     let ray = Ray::new(camera_pos, camera_forward);
-    look_at.0 = match octree.raycast(&ray) {
-        Some(hit_info) => {
+    //Debris is passed through, so nothing is placed on or reported of what is about to vanish.
+    //Bounds of ground are too, as terrain is hit by its own voxels below.
+    let hit = spatial.raycast(&ray, |entity| !passed.contains(entity));
+    look_at.0 = match (hit, terrain.raycast(&ray)) {
+        //Entity is hit only if it is in front of terrain.
+        (Some(hit_info), voxel_hit) if voxel_hit.as_ref().is_none_or(|v| hit_info.t < v.t) => {
             let pos = ray.point(hit_info.t + 0.001);
             let face = hit_info.aabb.face(pos);
             transform.translation = pos.round() + face;
@@ -356,8 +502,17 @@ fn camera_look_at(
             selection.valid = true;
             Some(hit_info)
         }
+        //Put on face of terrain cell.
+        (_, Some(hit)) => {
+            let face = hit.normal.as_vec3();
+            transform.translation = (hit.cell + hit.normal).as_vec3();
+            transform.rotation =
+                Quat::from_rotation_arc(Vec3::Y, face) * Quat::from_rotation_y(y_rot);
+            selection.valid = true;
+            None
+        }
         //If no result, checks root of tree's bound.
-        None => match BLUEPRINT_BOUND.intersects_ray(&ray) {
+        _ => match BLUEPRINT_BOUND.intersects_ray(&ray) {
             Some(len) => {
                 let pos = ray.point(len + 0.001);
                 let face = -BLUEPRINT_BOUND.face(pos);
@@ -373,25 +528,32 @@ fn camera_look_at(
             }
         },
     };
+    //Can't place where something already is.
+    if selection.valid {
+        let cell = OccupancyGrid::cell(transform.translation);
+        selection.valid = grid.is_footprint_free(&selection.buildable.collider, &transform)
+            && !terrain.get(cell).is_solid();
+    }
 }
 
 ///Places cube where camera looking at. Temporary.
 fn place(
     mut commands: Commands,
-    mut octree: Query<&mut Octree>,
+    mut spatial: Query<(&mut Spatial, &mut OccupancyGrid)>,
     state: Res<GlobalState>,
     selection: Query<(&Selection, &Transform)>,
-    input: Res<Input<MouseButton>>,
+    actions: Res<Input<InputAction>>,
     time: Res<Time>,
+    settings: Res<Settings>,
     mut press_time: Local<f32>,
 ) {
-    //Checks only when left click.
-    let mut place = input.just_pressed(MouseButton::Left);
+    //Checks only when place is pressed.
+    let mut place = actions.just_pressed(InputAction::Place);
     if !place {
         //Repeat place if button is pressed long enough.
-        if input.pressed(MouseButton::Left) {
+        if actions.pressed(InputAction::Place) {
             *press_time += time.delta_seconds();
-            if *press_time >= 1. {
+            if *press_time >= settings.long_press_delay {
                 place = true;
                 *press_time -= 0.1;
             }
@@ -404,46 +566,75 @@ fn place(
     if place {
         if selection.valid {
             //If there's a result, spawn a selection.
-            let children = selection.create();
-            let entity = commands
-                .spawn((
-                    TransformBundle {
-                        local: transform,
-                        ..default()
-                    },
-                    VisibilityBundle::default(),
-                    state.mark(),
-                    selection.collider.clone(),
-                ))
-                .with_children(|parent| {
-                    for bundle in children {
-                        parent.spawn(bundle);
-                    }
-                })
-                .id();
-            octree
-                .single_mut()
-                .insert(OctreeEntity::new(entity, &selection.collider, &transform));
+            let (mut spatial, mut grid) = spatial.single_mut();
+            spawn_tower(
+                &mut commands,
+                selection,
+                transform,
+                &state,
+                &mut spatial,
+                &mut grid,
+            );
         }
     }
 }
 
+///Spawns tower of selection and registers it to spatial queries.
+fn spawn_tower(
+    commands: &mut Commands,
+    selection: &Selection,
+    transform: Transform,
+    state: &GlobalState,
+    spatial: &mut Spatial,
+    grid: &mut OccupancyGrid,
+) {
+    let children = selection.create();
+    let entity = commands
+        .spawn((
+            TransformBundle {
+                local: transform,
+                ..default()
+            },
+            VisibilityBundle::default(),
+            state.mark(),
+            selection.buildable.collider.clone(),
+            Building {
+                name: selection.buildable.name,
+                cost: selection.buildable.cost,
+            },
+        ))
+        .with_children(|parent| {
+            for bundle in children {
+                parent.spawn(bundle);
+            }
+        })
+        .id();
+    let collider = &selection.buildable.collider;
+    spatial.insert_static(OctreeEntity::new(entity, collider, &transform));
+    grid.insert(entity, collider, &transform);
+}
+
 ///Replaces cube where camera looking at. Temporary.
 fn replace(
     mut commands: Commands,
-    mut octree: Query<&mut Octree>,
+    mut spatial: Query<(&mut Spatial, &mut OccupancyGrid)>,
     camera: Query<&LookAt, With<Camera>>,
-    input: Res<Input<MouseButton>>,
+    placed: Query<(&Collider, &Transform, &Children), (Without<Obstacle>, Without<Debris>)>,
+    parts: Query<(&Handle<Mesh>, &Handle<StandardMaterial>, &Transform)>,
+    meshes: Res<Assets<Mesh>>,
+    state: Res<GlobalState>,
+    actions: Res<Input<InputAction>>,
     time: Res<Time>,
+    settings: Res<Settings>,
     mut press_time: Local<f32>,
 ) {
-    //Checks only when right click.
-    let mut replace = input.just_pressed(MouseButton::Right);
+    //Checks only when remove is pressed.
+    let mut replace = actions.just_pressed(InputAction::Remove);
     if !replace {
         //Repeat place if button is pressed long enough.
-        if input.pressed(MouseButton::Right) {
+        if actions.pressed(InputAction::Remove) {
             *press_time += time.delta_seconds();
-            if *press_time >= 1. {
+            if *press_time >= settings.long_press_delay {
                 replace = true;
                 *press_time -= 0.1;
             }
@@ -454,10 +645,76 @@ fn replace(
 
     if replace {
         if let Some(hit_info) = &camera.single().0 {
-            //If there's a result, despawn a cube.
-            if octree.single_mut().remove(hit_info.entity, hit_info.aabb){
-                commands.entity(hit_info.entity).despawn_recursive();
+            //If there's a result, break it into debris.
+            if let Ok((collider, transform, children)) = placed.get(hit_info.entity) {
+                let (mut spatial, mut grid) = spatial.single_mut();
+                //Whatever rests on it would be left floating.
+                if grid.carries(hit_info.entity, collider, transform) {
+                    return;
+                }
+                if spatial.remove_static(hit_info.entity, hit_info.aabb) {
+                    grid.remove(hit_info.entity, collider, transform);
+                    commands.entity(hit_info.entity).despawn_recursive();
+                    let count = children.len();
+                    for (i, child) in children.iter().enumerate() {
+                        if let Ok((mesh, material, local)) = parts.get(*child) {
+                            //Scatter parts around and upward, upper parts higher.
+                            let angle = i as f32 / count as f32 * TAU;
+                            let outward =
+                                transform.rotation * Vec3::new(angle.cos(), 0., angle.sin());
+                            //Collider fits part, or is a small ball until its mesh is loaded.
+                            let shape = meshes.get(mesh).and_then(Mesh::compute_aabb).map_or(
+                                Shape::Sphere { radius: 0.5 },
+                                |aabb| Shape::Cuboid {
+                                    center: aabb.center.into(),
+                                    half_size: aabb.half_extents.into(),
+                                },
+                            );
+                            commands.spawn((
+                                PbrBundle {
+                                    mesh: mesh.clone(),
+                                    material: material.clone(),
+                                    transform: transform.mul_transform(*local),
+                                    ..default()
+                                },
+                                Collider::from_shape(shape),
+                                Velocity {
+                                    linear: outward * 2. + Vec3::Y * (4. + i as f32),
+                                    angular: outward.cross(Vec3::Y) * 3.,
+                                },
+                                Dynamic,
+                                Debris(Timer::from_seconds(DEBRIS_LIFETIME, TimerMode::Once)),
+                                state.mark(),
+                            ));
+                        }
+                    }
+                }
             }
         }
     }
 }
+
+///Starts next wave when its time comes.
+fn advance_wave(
+    mut wave: ResMut<Wave>,
+    mut notifications: EventWriter<Notification>,
+    time: Res<Time>,
+) {
+    if wave.timer.tick(time.delta()).just_finished() {
+        wave.number += 1;
+        notifications.send(Notification(WAVE_STARTED_TEXT));
+    }
+}
+
+///Despawns debris when its time is up.
+fn despawn_debris(
+    mut commands: Commands,
+    mut debris: Query<(Entity, &mut Debris)>,
+    time: Res<Time>,
+) {
+    for (entity, mut debris) in debris.iter_mut() {
+        if debris.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
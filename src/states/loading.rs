@@ -0,0 +1,225 @@
+use crate::{
+    asset::{Fonts, LoadProgress},
+    func::*,
+    locale::Locale,
+    states::{fade::Fade, *},
+    theme::{TextColor, Theme},
+    ui::*,
+};
+
+use bevy::{app::AppExit, prelude::*};
+
+///Batch setup for Loading.
+/// - Goes to state of AfterLoading once every asset is ready.
+/// - If some fail, offers to load them again, to go on without them or to quit.
+/// - Closing window quits right away, as there is nothing to lose yet.
+pub struct LoadingPlugin;
+
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AfterLoading>()
+            .add_system_set_to_stage(
+                CoreStage::PreUpdate,
+                on_enter(AppState::Loading).with_system(setup),
+            )
+            .add_system_set_to_stage(
+                CoreStage::Update,
+                on_update(AppState::Loading)
+                    .with_system(loading)
+                    .with_system(failure_options)
+                    .with_system(exit_close_requested),
+            )
+            .add_system_set_to_stage(
+                CoreStage::PostUpdate,
+                on_update(AppState::Loading)
+                    .with_system(navigate::<HierarchyMark<0>>.label(NavigationSystem)),
+            );
+    }
+}
+
+///State that Loading goes to, which is Main menu unless launch options ask for other one.
+#[derive(Resource)]
+pub struct AfterLoading(pub AppState);
+
+impl Default for AfterLoading {
+    fn default() -> Self {
+        Self(AppState::MainMenu(None))
+    }
+}
+
+impl AfterLoading {
+    ///Main menu fades in. State asked on launch is a shortcut, so it is entered right away.
+    fn enter(&self, state: &mut GlobalState) -> Result<(), StateError> {
+        match self.0 {
+            AppState::MainMenu(None) => state.replace_faded(self.0, Fade::default()),
+            to => state.replace(to),
+        }
+    }
+}
+
+///Mark node that fills as assets are loaded.
+#[derive(Component)]
+struct LoadingBar;
+
+///Mark text that shows count or failures.
+#[derive(Component)]
+struct LoadingText;
+
+///Mark row that holds options once some asset fails.
+#[derive(Component)]
+struct FailureOptions;
+
+///Setup system in Loading.
+fn setup(mut commands: Commands, state: Res<GlobalState>, fonts: Res<Fonts>, theme: Res<Theme>) {
+    //ui camera, shared with Main menu.
+    commands.spawn((
+        Camera2dBundle::default(),
+        state.mark().persist([AppState::MainMenu(None)]),
+    ));
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    flex_direction: FlexDirection::ColumnReverse,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            state.mark(),
+        ))
+        .with_children(|parent| {
+            parent.spawn(create_text(
+                LOADING_TEXT,
+                &fonts,
+                &theme,
+                40.0,
+                TextColor::Bright,
+            ));
+            //bar
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(50.0), Val::Px(16.0)),
+                        margin: UiRect::all(Val::Px(16.0)),
+                        ..default()
+                    },
+                    background_color: theme.button_hover.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                size: Size::new(Val::Percent(0.0), Val::Percent(100.0)),
+                                ..default()
+                            },
+                            background_color: theme.text_bright.into(),
+                            ..default()
+                        },
+                        LoadingBar,
+                    ));
+                });
+            parent.spawn((
+                create_value_text("", &fonts, &theme, 20.0, TextColor::Bright),
+                LoadingText,
+            ));
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(100.0), Val::Px(80.0)),
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    ..default()
+                },
+                FailureOptions,
+            ));
+        });
+}
+
+///Shows progress and leaves once every asset is ready.
+fn loading(
+    progress: Res<LoadProgress>,
+    after: Res<AfterLoading>,
+    locale: Res<Locale>,
+    mut bar: Query<&mut Style, With<LoadingBar>>,
+    mut text: Query<&mut Text, With<LoadingText>>,
+    mut state: ResMut<GlobalState>,
+) {
+    for mut style in bar.iter_mut() {
+        style.size.width = Val::Percent(progress.ratio() * 100.0);
+    }
+    for mut text in text.iter_mut() {
+        text.sections[0].value = if progress.failed.is_empty() {
+            format!("{} / {}", progress.loaded, progress.total)
+        } else {
+            format!(
+                "{}: {}",
+                locale.get(FAILED_TEXT),
+                progress
+                    .failed
+                    .iter()
+                    .map(|(name, _)| *name)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        };
+    }
+    if progress.is_done() && !state.is_changing() {
+        let _ = after.enter(&mut state);
+    }
+}
+
+///Shows retry, continue and quit buttons while some asset has failed.
+fn failure_options(
+    mut commands: Commands,
+    progress: Res<LoadProgress>,
+    fonts: Res<Fonts>,
+    theme: Res<Theme>,
+    options: Query<(Entity, Option<&Children>), With<FailureOptions>>,
+) {
+    for (entity, children) in options.iter() {
+        let shown = children.is_some_and(|children| !children.is_empty());
+        if progress.failed.is_empty() {
+            if shown {
+                commands.entity(entity).despawn_descendants();
+            }
+            continue;
+        }
+        if shown {
+            continue;
+        }
+        commands.entity(entity).with_children(|parent| {
+            let buttons: [(&str, Action); 3] = [
+                (
+                    RETRY_TEXT,
+                    Action::new(
+                        |progress: Res<LoadProgress>, asset_server: Res<AssetServer>| {
+                            progress.retry(&asset_server);
+                        },
+                    ),
+                ),
+                (
+                    CONTINUE_TEXT,
+                    Action::new(|mut g: ResMut<GlobalState>, after: Res<AfterLoading>| {
+                        let _ = after.enter(&mut g);
+                    }),
+                ),
+                (
+                    QUIT_TEXT,
+                    Action::new(|mut exit: EventWriter<AppExit>| exit.send(AppExit)),
+                ),
+            ];
+            for (text, action) in buttons {
+                parent
+                    .spawn((create_button(&theme), action, HierarchyMark::<0>))
+                    .with_children(|parent| {
+                        parent.spawn(create_text(text, &fonts, &theme, 30.0, TextColor::Bright));
+                    });
+            }
+        });
+    }
+}
@@ -1,4 +1,12 @@
-use crate::{asset::Fonts, func::*, states::*, ui::*};
+use crate::{
+    asset::Fonts,
+    func::*,
+    locale::Locale,
+    states::{fade::Fade, *},
+    terrain::generate::MapSeed,
+    theme::{TextColor, Theme},
+    ui::*,
+};
 
 use bevy::prelude::*;
 
@@ -9,69 +17,136 @@ impl Plugin for MainMenuPlugin {
     fn build(&self, app: &mut App) {
         app.add_system_set_to_stage(
             CoreStage::PreUpdate,
-            SystemSet::on_enter(PreUpdateStageState::MainMenu).with_system(setup),
+            on_enter(AppState::MainMenu(None)).with_system(setup),
         )
         .add_system_set_to_stage(
             CoreStage::Update,
-            SystemSet::on_update(UpdateStageState::MainMenu)
-                .with_system(button)
+            on_update(AppState::MainMenu(None))
+                .with_system(map_text)
                 .with_system(close_requested),
+        )
+        .add_system_set_to_stage(
+            CoreStage::PostUpdate,
+            on_update(AppState::MainMenu(None))
+                .with_system(navigate::<HierarchyMark<0>>.label(NavigationSystem)),
         );
     }
 }
 
 ///Setup system in Main menu.
-fn setup(mut commands: Commands, state: Res<GlobalState>, res: Res<Fonts>) {
-    //ui camera
-    commands.spawn((Camera2dBundle::default(), state.mark()));
+fn setup(
+    mut commands: Commands,
+    state: Res<GlobalState>,
+    res: Res<Fonts>,
+    theme: Res<Theme>,
+    map_seed: Res<MapSeed>,
+    locale: Res<Locale>,
+    cameras: Query<(), With<Camera2d>>,
+) {
+    //ui camera, unless one persists from Loading.
+    if cameras.is_empty() {
+        commands.spawn((Camera2dBundle::default(), state.mark()));
+    }
     //play button
     commands
         .spawn((
-            create_button(),
+            create_button(&theme),
+            state.mark(),
+            Action::new(|mut g: ResMut<GlobalState>| {
+                let _ = g.replace_faded(AppState::InGame(None), Fade::default());
+            }),
+            HierarchyMark::<0>,
+        ))
+        .with_children(|parent| {
+            parent.spawn(create_text(
+                PLAY_TEXT,
+                &res,
+                &theme,
+                30.0,
+                TextColor::Bright,
+            ));
+        });
+    //map button
+    commands
+        .spawn((
+            create_button(&theme),
             state.mark(),
-            Action::<for<'a> fn(&'a mut GlobalState)>::new(|g: &mut GlobalState| {
-                g.replace(AppState::InGame)
+            Action::new(|mut m: ResMut<MapSeed>| m.cycle()),
+            HierarchyMark::<0>,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                create_value_text(
+                    map_name(&map_seed, &locale),
+                    &res,
+                    &theme,
+                    20.0,
+                    TextColor::Bright,
+                ),
+                MapText,
+            ));
+        });
+    //settings button
+    commands
+        .spawn((
+            create_button(&theme),
+            state.mark(),
+            Action::new(|mut g: ResMut<GlobalState>| {
+                let _ = g.push(MainMenuState::Settings);
             }),
             HierarchyMark::<0>,
         ))
         .with_children(|parent| {
-            parent.spawn(create_text(PLAY_TEXT, &res, 30.0, TEXT_COLOR_BRIGHT));
+            parent.spawn(create_text(
+                SETTINGS_TEXT,
+                &res,
+                &theme,
+                30.0,
+                TextColor::Bright,
+            ));
         });
     //exit button
     commands
         .spawn((
-            create_button(),
+            create_button(&theme),
             state.mark(),
-            Action::<for<'a> fn(&'a mut GlobalState)>::new(|g: &mut GlobalState| g.push_exit()),
+            Action::new(|mut g: ResMut<GlobalState>| {
+                let _ = g.push_exit();
+            }),
             HierarchyMark::<0>,
         ))
         .with_children(|parent| {
-            parent.spawn(create_text(EXIT_TEXT, &res, 30.0, TEXT_COLOR_BRIGHT));
+            parent.spawn(create_text(
+                EXIT_TEXT,
+                &res,
+                &theme,
+                30.0,
+                TextColor::Bright,
+            ));
         });
 }
 
-///Buttons interaction system.
-fn button(
-    mut interaction_query: Query<
-        (
-            &Interaction,
-            &mut BackgroundColor,
-            &Action<for<'a> fn(&'a mut GlobalState)>,
-            &HierarchyMark<0>,
-        ),
-        (Changed<Interaction>, With<Button>),
-    >,
-    mut state: ResMut<GlobalState>,
+///Mark text that shows which map will be played.
+#[derive(Component)]
+struct MapText;
+
+///Map of seed in current language.
+fn map_name(map_seed: &MapSeed, locale: &Locale) -> String {
+    match map_seed.0 {
+        Some(seed) => format!("{}: {seed}", locale.get(MAP_TEXT)),
+        None => format!("{}: {}", locale.get(MAP_TEXT), locale.get(FLAT_TEXT)),
+    }
+}
+
+///Keeps map text up to date.
+fn map_text(
+    map_seed: Res<MapSeed>,
+    locale: Res<Locale>,
+    mut query: Query<&mut Text, With<MapText>>,
 ) {
-    for (interaction, mut color, func, _) in interaction_query.iter_mut() {
-        match *interaction {
-            Interaction::Clicked => func.run(&mut *state),
-            Interaction::Hovered => {
-                *color = BUTTON_COLOR_HOVER.into();
-            }
-            Interaction::None => {
-                *color = BUTTON_COLOR_NONE.into();
-            }
+    if map_seed.is_changed() || locale.is_changed() {
+        for mut text in query.iter_mut() {
+            text.sections[0].value = map_name(&map_seed, &locale);
         }
     }
 }
@@ -1,411 +1,738 @@
+pub mod controls;
+pub mod debug;
+pub mod fade;
+pub mod hud;
 pub mod in_game;
+pub mod loading;
 pub mod main_menu;
+pub mod palette;
+pub mod pause;
+pub mod settings;
 
 use crate::{
-    ui::{exit_close_requested, exit_esc, exit_no_button, exit_yes_button, setup_exit},
-    unreachable_release,
+    states::{
+        debug::StateDebugPlugin,
+        fade::{Fade, FadePlugin, ScreenFade},
+    },
+    ui::{exit_close_requested, exit_esc, navigate, setup_exit, AppExitMark, NavigationSystem},
 };
 
-use bevy::{ecs::system::SystemState, prelude::*};
-
-///Auto declare and impl states' per stages common parts.
-macro_rules! stage_states {
-    ($global_name:ident; $($stage_name:ident),*; $locals:tt $global:tt) => {
-        #[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
-        pub enum $global_name $locals
-        $(
-            stage_states!(@ $stage_name $locals $global);
-        )*
-    };
-    (@ $stage_name:ident {$($locals:ident),*} {$($global:ident),*}) => {
-        #[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
-        pub enum $stage_name {
-            $($locals,)*
-            $($global),*
-        }
-    }
+use bevy::{
+    ecs::{schedule::ShouldRun, system::SystemState},
+    prelude::*,
+    utils::HashMap,
+};
+
+///Every state of app. Same value drives systems of every stage.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub enum AppState {
+    Loading,
+    MainMenu(Option<MainMenuState>),
+    InGame(Option<InGameState>),
+    AppExit,
 }
 
-stage_states!(
-    AppState;
-    FirstStageState,
-    PreUpdateStageState,
-    UpdateStageState,
-    PostUpdateStageState,
-    LastStageState;
-    {
-        MainMenu,
-        InGame
+impl AppState {
+    ///Whether both are same major state regardless of minor state.
+    pub fn is_same_major(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (AppState::Loading, AppState::Loading)
+                | (AppState::MainMenu(_), AppState::MainMenu(_))
+                | (AppState::InGame(_), AppState::InGame(_))
+        )
     }
-    {
-        AppExit
+
+    ///Whether there is no minor state on top.
+    pub fn is_major(&self) -> bool {
+        matches!(
+            self,
+            AppState::Loading | AppState::MainMenu(None) | AppState::InGame(None)
+        )
     }
-);
 
-///Trait for States that could be pop.
-pub trait PopState: Sized {
-    fn pop(self) -> Option<Self>;
+    ///Major state under minor one.
+    pub fn major(&self) -> Self {
+        match self {
+            AppState::MainMenu(_) => AppState::MainMenu(None),
+            AppState::InGame(_) => AppState::InGame(None),
+            state => *state,
+        }
+    }
 }
 
 ///Trait for States that can push itself to state.
 pub trait PushState {
-    fn push(self, parent: &mut AppState);
+    ///None if parent has no space for this state.
+    fn push(self, parent: AppState) -> Option<AppState>;
+}
+
+///Minor states under main menu.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub enum MainMenuState {
+    Settings,
+    Controls,
+}
+
+impl PushState for MainMenuState {
+    fn push(self, parent: AppState) -> Option<AppState> {
+        match parent {
+            AppState::MainMenu(_) => Some(AppState::MainMenu(Some(self))),
+            _ => None,
+        }
+    }
+}
+
+///Minor states under in game.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub enum InGameState {
+    Pause,
+    Settings,
+    Controls,
 }
 
-impl PopState for AppState {
-    fn pop(self) -> Option<Self> {
-        // match self {
-        //     AppState::MainMenu(Some(m)) => Some(AppState::MainMenu(m.pop())),
-        //     AppState::InGame(Some(i)) => Some(AppState::InGame(i.pop())),
-        //     _ => None,
-        // }
-        None
+impl PushState for InGameState {
+    fn push(self, parent: AppState) -> Option<AppState> {
+        match parent {
+            AppState::InGame(_) => Some(AppState::InGame(Some(self))),
+            _ => None,
+        }
     }
 }
 
-// #[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
-// pub enum MainMenuState {
-//     AppExit,
-// }
-//
-// impl PopState for MainMenuState {
-//     fn pop(self) -> Option<Self> {
-//         match self {
-//             MainMenuState::AppExit => None,
-//         }
-//     }
-// }
-//
-// impl PushState for MainMenuState {//
-//     fn push(self, parent: &mut AppState) {
-//         match *parent {
-//             AppState::MainMenu(None) => *parent = AppState::MainMenu(Some(self)),
-//             _ => unreachable_release!("There is no space to push"),
-//         }
-//     }
-// }
-//
-// #[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
-// pub enum InGameState {
-//     AppExit,
-// }
-//
-// impl PopState for InGameState {
-//     fn pop(self) -> Option<Self> {
-//         match self {
-//             InGameState::AppExit => None,
-//         }
-//     }
-// }
-//
-// impl PushState for InGameState {
-//     fn push(self, parent: &mut AppState) {
-//         match *parent {
-//             AppState::InGame(None) => *parent = AppState::InGame(Some(self)),
-//             _ => unreachable_release!("There is no space to push"),
-//         }
-//     }
-// }
+///Why requested state change is refused.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum StateError {
+    ///Screen is fading out for change that is already requested.
+    AlreadyTransitioning,
+    ///Target is the same major state, or it isn't major state at all.
+    SameState,
+    ///There is no minor or exit state to pop.
+    NothingToPop,
+    ///Current state has no space for pushed one.
+    NoSpace,
+}
+
+impl std::fmt::Display for StateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StateError::AlreadyTransitioning => write!(f, "Already in state transition"),
+            StateError::SameState => write!(
+                f,
+                "Already in that major state or target state hierarchy is not 0"
+            ),
+            StateError::NothingToPop => write!(f, "There is no state to pop"),
+            StateError::NoSpace => write!(f, "There is no space to push"),
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
 
 ///Intentionally privacy for external mods to avoid disruptive mistakes.
 mod global {
-    use crate::{states::*, unreachable_release};
+    use crate::states::*;
+
+    use std::collections::VecDeque;
 
     use bevy::prelude::Component;
 
-    ///Metadata of how much state is stacked and whether about to exit.
+    ///Metadata of how much state is stacked.
     #[derive(Clone, Copy, Ord, PartialOrd, Debug, Eq, PartialEq)]
     pub struct Hierarchy {
         value: u32,
-        exit: bool,
     }
 
     impl Hierarchy {
         pub const fn new<const N: u32>() -> Self {
-            Self {
-                value: N,
-                exit: false,
-            }
+            Self { value: N }
         }
 
         fn reset(&mut self) {
             self.value = 0;
-            self.exit = false;
         }
 
-        ///increment only hierarchy.
-        fn _increment(&mut self) {
+        fn increment(&mut self) {
             self.value += 1
         }
 
-        ///decrement only hierarchy.
-        fn _decrement(&mut self) {
+        fn decrement(&mut self) {
             self.value -= 1
         }
+    }
 
-        ///sets only whether about to exit.
-        fn set_exit(&mut self, exit: bool) {
-            self.exit = exit
-        }
+    ///What the last applied state change did. Valid until the start of next frame.
+    #[derive(Default)]
+    struct Transition {
+        way: Option<StateChangeWay>,
+        ///From top to bottom.
+        exited: Vec<AppState>,
+        ///Hierarchy of the lowest exited state.
+        exited_base: u32,
+        entered: Option<AppState>,
+        paused: Option<AppState>,
+        resumed: Option<AppState>,
+    }
 
-        fn is_exit(&self) -> bool {
-            self.exit
-        }
+    ///Number of applied transitions kept in history.
+    const HISTORY_LEN: usize = 32;
+
+    ///State change waiting to be applied.
+    struct Request {
+        way: StateChangeWay,
+        ///State on top after change.
+        to: AppState,
+        fade: Option<Fade>,
+        ///Whether fade is already started.
+        fading: bool,
     }
 
     ///A unique global state metadata.
     #[derive(Resource)]
     pub struct GlobalState {
-        app_state: AppState,
+        ///Applied states from bottom to top. Only empty before the first frame.
+        stack: Vec<AppState>,
         hierarchy: Hierarchy,
-        state_change_way: StateChangeWay,
+        ///Requested changes. One is applied per frame, in order.
+        queue: VecDeque<Request>,
+        ///Stack after every queued change is applied. Requests are checked against it.
+        queued_stack: Vec<AppState>,
+        transition: Transition,
+        ///Applied transitions with elapsed seconds, from oldest.
+        history: VecDeque<(f64, StateTransition)>,
     }
 
     impl GlobalState {
+        ///Initial state is entered on the first frame.
         pub fn new(initial: AppState) -> Self {
             Self {
-                app_state: initial,
+                stack: Vec::new(),
                 hierarchy: Hierarchy::new::<0>(),
-                state_change_way: StateChangeWay::None,
+                queue: VecDeque::from([Request {
+                    way: StateChangeWay::Replace,
+                    to: initial,
+                    fade: None,
+                    fading: false,
+                }]),
+                queued_stack: vec![initial],
+                transition: Transition::default(),
+                history: VecDeque::with_capacity(HISTORY_LEN),
             }
         }
 
-        pub fn is_exit(&self) -> bool {
-            self.hierarchy.is_exit()
+        ///Applied state on top.
+        pub fn current(&self) -> AppState {
+            *self.stack.last().expect("State is not applied yet")
         }
 
         ///Mark to entities that stick to state.
         pub fn mark(&self) -> StateMark {
-            StateMark(self.app_state, self.hierarchy)
+            StateMark {
+                state: self.current(),
+                hierarchy: self.hierarchy,
+                scope: MarkScope::State,
+                hide: false,
+            }
+        }
+
+        ///Whether change is requested but not applied yet.
+        pub fn is_changing(&self) -> bool {
+            !self.queue.is_empty()
+        }
+
+        pub fn is_current(&self, state: AppState) -> bool {
+            self.stack.last() == Some(&state)
+        }
+
+        pub fn is_entered(&self, state: AppState) -> bool {
+            self.transition.entered == Some(state)
+        }
+
+        pub fn is_exited(&self, state: AppState) -> bool {
+            self.transition.exited.contains(&state)
+        }
+
+        pub fn is_paused(&self, state: AppState) -> bool {
+            self.transition.paused == Some(state)
+        }
+
+        pub fn is_resumed(&self, state: AppState) -> bool {
+            self.transition.resumed == Some(state)
+        }
+
+        ///Fade of change to be applied next, only once.
+        pub fn start_fade(&mut self) -> Option<Fade> {
+            match self.queue.front_mut() {
+                Some(request) if !request.fading => {
+                    request.fading = true;
+                    request.fade
+                }
+                _ => None,
+            }
+        }
+
+        ///Forgets last transition without applying requested change.
+        pub fn forget_transition(&mut self) {
+            self.transition = Transition::default();
+        }
+
+        ///Every state that last transition touched, and how.
+        pub fn hooks(&self) -> impl Iterator<Item = (AppState, StateHook)> + '_ {
+            let transition = &self.transition;
+            (transition.exited.iter().map(|s| (*s, StateHook::Exit)))
+                .chain(transition.paused.map(|s| (s, StateHook::Pause)))
+                .chain(transition.entered.map(|s| (s, StateHook::Enter)))
+                .chain(transition.resumed.map(|s| (s, StateHook::Resume)))
         }
 
-        pub fn should_change(&self) -> bool {
-            self.state_change_way != StateChangeWay::None
+        ///Applied transitions with elapsed seconds, from oldest.
+        pub fn history(&self) -> impl DoubleEndedIterator<Item = &(f64, StateTransition)> {
+            self.history.iter()
         }
 
-        ///Applies state change via closure.
-        pub fn propagate_change(&mut self, mut f: impl FnMut(&AppState, bool, &StateChangeWay)) {
-            if self.state_change_way == StateChangeWay::None {
-                unreachable_release!("No state transition expected");
+        ///Applies the oldest requested change to stack. Otherwise forgets last transition.
+        pub fn apply_change(&mut self, elapsed: f64) -> Option<StateTransition> {
+            let from = self.stack.last().copied();
+            let mut transition = Transition::default();
+            let request = self.queue.pop_front();
+            if let Some(Request { way, to, .. }) = request {
+                transition.way = Some(way);
+                match way {
+                    StateChangeWay::Replace => {
+                        transition.exited = self.stack.drain(..).rev().collect();
+                        transition.entered = Some(to);
+                        self.stack.push(to);
+                        self.hierarchy.reset();
+                    }
+                    StateChangeWay::Push => {
+                        transition.paused = self.stack.last().copied();
+                        transition.entered = Some(to);
+                        self.stack.push(to);
+                        self.hierarchy.increment();
+                    }
+                    StateChangeWay::Pop => {
+                        transition.exited = self.stack.pop().into_iter().collect();
+                        transition.exited_base = self.stack.len() as u32;
+                        transition.resumed = self.stack.last().copied();
+                        self.hierarchy.decrement();
+                    }
+                }
             }
-            f(&self.app_state, self.is_exit(), &self.state_change_way);
-            self.state_change_way = StateChangeWay::None;
+            self.transition = transition;
+            let transition =
+                request.map(|Request { way, to, .. }| StateTransition { from, to, way });
+            if let Some(transition) = transition {
+                if self.history.len() == HISTORY_LEN {
+                    self.history.pop_front();
+                }
+                self.history.push_back((elapsed, transition));
+            }
+            transition
         }
 
         ///Whether state of entity originated is outdated.
         pub fn should_clear(&self, other: &StateMark) -> bool {
-            self.hierarchy < other.1 || (self.hierarchy == other.1 && self.app_state != other.0)
+            let outdated = self.hierarchy < other.hierarchy
+                || (self.hierarchy == other.hierarchy && self.current() != other.state);
+            match &other.scope {
+                MarkScope::State => outdated,
+                MarkScope::Persist(states) => {
+                    outdated && !self.stack.iter().any(|state| states.contains(state))
+                }
+                MarkScope::OnPop => {
+                    self.transition.way == Some(StateChangeWay::Pop)
+                        && self.hierarchy < other.hierarchy
+                }
+                MarkScope::OnExitDepth(depth) => {
+                    let base = self.transition.exited_base;
+                    (base..base + self.transition.exited.len() as u32).contains(depth)
+                }
+            }
         }
 
-        ///Force major state. Equivalent to Schedule::replace.
-        pub fn replace(&mut self, to: AppState) {
-            if match (self.app_state, to) {
-                (AppState::MainMenu, AppState::MainMenu) | (AppState::InGame, AppState::InGame) => {
-                    true
+        ///Whether other state is pushed above the one entity originated.
+        pub fn is_covered(&self, other: &StateMark) -> bool {
+            self.hierarchy > other.hierarchy
+        }
+
+        ///Queues request if it is valid against queued stack. Refusal is logged.
+        fn request(
+            &mut self,
+            way: StateChangeWay,
+            to: Result<AppState, StateError>,
+            fade: Option<Fade>,
+        ) -> Result<(), StateError> {
+            let result = if self.queue.iter().any(|request| request.fade.is_some()) {
+                Err(StateError::AlreadyTransitioning)
+            } else {
+                to
+            };
+            match result {
+                Ok(to) => {
+                    match way {
+                        StateChangeWay::Replace => self.queued_stack.clear(),
+                        StateChangeWay::Pop => {
+                            self.queued_stack.pop();
+                        }
+                        StateChangeWay::Push => {}
+                    }
+                    if way != StateChangeWay::Pop {
+                        self.queued_stack.push(to);
+                    }
+                    self.queue.push_back(Request {
+                        way,
+                        to,
+                        fade,
+                        fading: false,
+                    });
+                    Ok(())
+                }
+                Err(err) => {
+                    warn!("{way:?} refused: {err}");
+                    Err(err)
                 }
-                _ => false,
-            } {
-                unreachable_release!(
-                    "Already in that major state or target state hierarchy is not 0"
-                );
-            } else if self.state_change_way != StateChangeWay::None {
-                unreachable_release!("Already in state transition");
             }
-            self.app_state = to;
-            self.hierarchy.reset();
-            self.state_change_way = StateChangeWay::Replace;
+        }
+
+        ///State on top after every queued change.
+        fn queued_top(&self) -> AppState {
+            *self
+                .queued_stack
+                .last()
+                .expect("Stack is never emptied by request")
+        }
+
+        ///Force major state. Equivalent to Schedule::replace.
+        pub fn replace(&mut self, to: AppState) -> Result<(), StateError> {
+            self.replace_with(to, None)
+        }
+
+        ///Replace that changes state when screen is covered by fade.
+        pub fn replace_faded(&mut self, to: AppState, fade: Fade) -> Result<(), StateError> {
+            self.replace_with(to, Some(fade))
+        }
+
+        fn replace_with(&mut self, to: AppState, fade: Option<Fade>) -> Result<(), StateError> {
+            let to = if self.queued_top().is_same_major(&to) || !to.is_major() {
+                Err(StateError::SameState)
+            } else {
+                Ok(to)
+            };
+            self.request(StateChangeWay::Replace, to, fade)
         }
 
         ///Stacks minor state. Equivalent to Schedule::push
-        pub fn _push<Child: PushState>(&mut self, child: Child) {
-            if self.state_change_way != StateChangeWay::None {
-                unreachable_release!("Already in state transition");
-            }
-            child.push(&mut self.app_state);
-            self.hierarchy._increment();
-            self.state_change_way = StateChangeWay::Push;
+        pub fn push<Child: PushState>(&mut self, child: Child) -> Result<(), StateError> {
+            let to = child.push(self.queued_top()).ok_or(StateError::NoSpace);
+            self.request(StateChangeWay::Push, to, None)
         }
 
         ///Stacks exit state. Equivalent to Schedule::push
-        pub fn push_exit(&mut self) {
-            if self.state_change_way != StateChangeWay::None {
-                unreachable_release!("Already in state transition");
-            }
-            self.hierarchy.set_exit(true);
-            self.state_change_way = StateChangeWay::Push;
+        pub fn push_exit(&mut self) -> Result<(), StateError> {
+            let to = match self.queued_top() {
+                AppState::AppExit => Err(StateError::NoSpace),
+                _ => Ok(AppState::AppExit),
+            };
+            self.request(StateChangeWay::Push, to, None)
         }
 
         ///Releases minor state. Equivalent to Schedule::pop
-        pub fn _pop(&mut self) {
-            if self.state_change_way != StateChangeWay::None {
-                unreachable_release!("Already in state transition");
-            }
-            self.app_state = match self.app_state.pop() {
-                Some(a) => a,
-                _ => unreachable_release!("Already in that state"),
+        pub fn pop(&mut self) -> Result<(), StateError> {
+            let len = self.queued_stack.len();
+            let to = match self.queued_top() {
+                AppState::AppExit => Err(StateError::NothingToPop),
+                _ if len < 2 => Err(StateError::NothingToPop),
+                _ => Ok(self.queued_stack[len - 2]),
             };
-            self.hierarchy._decrement();
-            self.state_change_way = StateChangeWay::Pop;
+            self.request(StateChangeWay::Pop, to, None)
         }
 
-        ///Releases exit state. Equivalent to Schedule::pop
-        pub fn pop_exit(&mut self) {
-            if self.state_change_way != StateChangeWay::None {
-                unreachable_release!("Already in state transition");
+        ///Goes to previous screen whatever current one is.
+        /// - Pops exit or minor state if there is.
+        /// - Otherwise replaces with major state that was left last.
+        pub fn back(&mut self) -> Result<(), StateError> {
+            match self.queued_top() {
+                AppState::AppExit => self.pop_exit(),
+                _ if self.queued_stack.len() > 1 => self.pop(),
+                _ => {
+                    let previous = self
+                        .history
+                        .iter()
+                        .rev()
+                        .find(|(_, t)| t.way == StateChangeWay::Replace)
+                        .and_then(|(_, t)| t.from)
+                        .map(|from| from.major())
+                        .filter(|from| *from != AppState::Loading);
+                    match previous {
+                        Some(to) => self.replace_faded(to, Fade::default()),
+                        None => self.request(
+                            StateChangeWay::Replace,
+                            Err(StateError::NothingToPop),
+                            None,
+                        ),
+                    }
+                }
             }
-            self.hierarchy.set_exit(false);
-            self.state_change_way = StateChangeWay::Pop;
+        }
+
+        ///Releases exit state. Equivalent to Schedule::pop
+        pub fn pop_exit(&mut self) -> Result<(), StateError> {
+            let len = self.queued_stack.len();
+            let to = match self.queued_top() {
+                AppState::AppExit => Ok(self.queued_stack[len - 2]),
+                _ => Err(StateError::NothingToPop),
+            };
+            self.request(StateChangeWay::Pop, to, None)
         }
     }
 
     ///Describes how state change be propagated.
     #[derive(Clone, Copy, Eq, PartialEq, Debug)]
     pub enum StateChangeWay {
-        None,
         Replace,
         Push,
         Pop,
     }
 
+    ///When entity is outdated.
+    enum MarkScope {
+        ///State or hierarchy it originated is gone.
+        State,
+        ///Same as State, but survives while any of listed states is on stack.
+        Persist(Vec<AppState>),
+        ///Only when hierarchy it originated is popped. Survives replace.
+        OnPop,
+        ///When state of given hierarchy is exited, by pop or replace.
+        OnExitDepth(u32),
+    }
+
     ///State metadata component for entity.
     #[derive(Component)]
-    pub struct StateMark(AppState, Hierarchy);
+    pub struct StateMark {
+        state: AppState,
+        hierarchy: Hierarchy,
+        scope: MarkScope,
+        ///Hidden while covered by pushed state.
+        hide: bool,
+    }
+
+    impl StateMark {
+        ///Survives while any of states is on stack, so it can be shared between screens.
+        pub fn persist(mut self, states: impl IntoIterator<Item = AppState>) -> Self {
+            self.scope = MarkScope::Persist(states.into_iter().collect());
+            self
+        }
+
+        ///Despawned only when popped. Survives replace, so it must be popped at some point.
+        pub fn until_pop(mut self) -> Self {
+            self.scope = MarkScope::OnPop;
+            self
+        }
+
+        ///Despawned when state of given hierarchy is exited, whichever state originated it.
+        pub fn until_exit_of(mut self, depth: u32) -> Self {
+            self.scope = MarkScope::OnExitDepth(depth);
+            self
+        }
+
+        ///Hidden while other state is pushed above, instead of staying visible below it.
+        ///Shown again once it is on top.
+        pub fn hidden(mut self) -> Self {
+            self.hide = true;
+            self
+        }
+
+        pub fn is_hidden(&self) -> bool {
+            self.hide
+        }
+
+        pub fn state(&self) -> AppState {
+            self.state
+        }
+    }
 }
 pub use global::*;
 
+///Event of every applied state change.
+#[derive(Clone, Copy, Debug)]
+pub struct StateTransition {
+    ///None only on entering initial state.
+    pub from: Option<AppState>,
+    pub to: AppState,
+    pub way: StateChangeWay,
+}
+
+///Moment of state that callback is run on.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub enum StateHook {
+    Enter,
+    Exit,
+    Pause,
+    Resume,
+}
+
+pub type StateCallback = Box<dyn Fn(&mut World, &StateTransition) + Send + Sync>;
+
+///Callbacks that run right after state change is applied, before any stage.
+#[derive(Resource, Default)]
+pub struct StateCallbacks(HashMap<(AppState, StateHook), Vec<StateCallback>>);
+
+impl StateCallbacks {
+    pub fn add(
+        &mut self,
+        state: AppState,
+        hook: StateHook,
+        callback: impl Fn(&mut World, &StateTransition) + Send + Sync + 'static,
+    ) {
+        self.0
+            .entry((state, hook))
+            .or_default()
+            .push(Box::new(callback));
+    }
+}
+
+///Shortcut to register state callbacks while building app.
+pub trait StateCallbackExt {
+    fn add_state_callback(
+        &mut self,
+        state: AppState,
+        hook: StateHook,
+        callback: impl Fn(&mut World, &StateTransition) + Send + Sync + 'static,
+    ) -> &mut Self;
+}
+
+impl StateCallbackExt for App {
+    fn add_state_callback(
+        &mut self,
+        state: AppState,
+        hook: StateHook,
+        callback: impl Fn(&mut World, &StateTransition) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.init_resource::<StateCallbacks>()
+            .world
+            .resource_mut::<StateCallbacks>()
+            .add(state, hook, callback);
+        self
+    }
+}
+
+///Runs once on the frame state is replaced or pushed to.
+pub fn on_enter(state: AppState) -> SystemSet {
+    SystemSet::new()
+        .with_run_criteria(move |g: Res<GlobalState>| ShouldRun::from(g.is_entered(state)))
+}
+
+///Runs every frame while state is on top, from the frame after it is entered so that entities
+///spawned on enter are there.
+pub fn on_update(state: AppState) -> SystemSet {
+    SystemSet::new().with_run_criteria(move |g: Res<GlobalState>| {
+        ShouldRun::from(g.is_current(state) && !g.is_entered(state))
+    })
+}
+
+///Runs once on the frame state is popped or unwound by replace.
+pub fn on_exit(state: AppState) -> SystemSet {
+    SystemSet::new()
+        .with_run_criteria(move |g: Res<GlobalState>| ShouldRun::from(g.is_exited(state)))
+}
+
+///Runs once on the frame other state is pushed above state.
+pub fn on_pause(state: AppState) -> SystemSet {
+    SystemSet::new()
+        .with_run_criteria(move |g: Res<GlobalState>| ShouldRun::from(g.is_paused(state)))
+}
+
+///Runs once on the frame state gets on top again by pop.
+pub fn on_resume(state: AppState) -> SystemSet {
+    SystemSet::new()
+        .with_run_criteria(move |g: Res<GlobalState>| ShouldRun::from(g.is_resumed(state)))
+}
+
 ///Batch setup of state managing.
-pub struct StatesPlugin;
+pub struct StatesPlugin {
+    ///State that is entered on first frame.
+    pub initial: AppState,
+}
+
+impl Default for StatesPlugin {
+    fn default() -> Self {
+        Self {
+            initial: AppState::Loading,
+        }
+    }
+}
 
 impl Plugin for StatesPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(GlobalState::new(AppState::MainMenu))
-            //First
+        app.insert_resource(GlobalState::new(self.initial))
+            .init_resource::<StateCallbacks>()
+            .add_event::<StateTransition>()
+            .add_plugin(FadePlugin)
+            .add_plugin(StateDebugPlugin)
             .add_system_to_stage(CoreStage::First, manage_state.at_start())
-            .add_state_to_stage(CoreStage::First, FirstStageState::MainMenu)
-            //PreUpdate
-            .add_state_to_stage(CoreStage::PreUpdate, PreUpdateStageState::MainMenu)
-            //Update
-            .add_state_to_stage(CoreStage::Update, UpdateStageState::MainMenu)
-            //PostUpdate
-            .add_state_to_stage(CoreStage::PostUpdate, PostUpdateStageState::MainMenu)
-            //Last
-            .add_state_to_stage(CoreStage::Last, LastStageState::MainMenu)
+            .add_system_to_stage(CoreStage::First, log_transition)
             //Exit
             .add_system_set_to_stage(
                 CoreStage::PreUpdate,
-                SystemSet::on_enter(PreUpdateStageState::AppExit).with_system(setup_exit),
+                on_enter(AppState::AppExit).with_system(setup_exit),
             )
             .add_system_set_to_stage(
                 CoreStage::Update,
-                SystemSet::on_update(UpdateStageState::AppExit)
-                    .with_system(exit_no_button)
-                    .with_system(exit_yes_button)
+                on_update(AppState::AppExit)
                     .with_system(exit_close_requested)
                     .with_system(exit_esc),
+            )
+            .add_system_set_to_stage(
+                CoreStage::PostUpdate,
+                on_update(AppState::AppExit)
+                    .with_system(navigate::<AppExitMark>.label(NavigationSystem)),
             );
     }
 }
 
-type ManageStateSystemState<'w> = SystemState<(
-    ResMut<'w, GlobalState>,
-    ResMut<'w, State<FirstStageState>>,
-    ResMut<'w, State<PreUpdateStageState>>,
-    ResMut<'w, State<UpdateStageState>>,
-    ResMut<'w, State<PostUpdateStageState>>,
-    ResMut<'w, State<LastStageState>>,
-)>;
+///Exclusive system that applies state change to every stage at once.
+fn manage_state(world: &mut World, clear_system_state: &mut ClearStateSystemState) {
+    let fade = world.resource_mut::<GlobalState>().start_fade();
+    let mut screen_fade = world.resource_mut::<ScreenFade>();
+    if let Some(fade) = fade {
+        screen_fade.start(fade);
+    }
+    //Requested change waits until screen is covered.
+    if screen_fade.is_fading_out() {
+        world.resource_mut::<GlobalState>().forget_transition();
+        return;
+    }
 
-///Exclusive system that propagates state change.
-fn manage_state(
-    world: &mut World,
-    system_state: &mut ManageStateSystemState,
-    clear_system_state: &mut ClearStateSystemState,
-) {
-    let (mut app_state, mut first, mut pre_update, mut update, mut post_update, mut last) =
-        system_state.get_mut(world);
-
-    //When global state is changed.
-    if app_state.should_change() {
-        app_state.propagate_change(|state, is_exit, change_way| {
-            //About to exit state.
-            if is_exit {
-                match change_way {
-                    StateChangeWay::Push => {
-                        first.push(FirstStageState::AppExit).unwrap();
-                        pre_update.push(PreUpdateStageState::AppExit).unwrap();
-                        update.push(UpdateStageState::AppExit).unwrap();
-                        post_update.push(PostUpdateStageState::AppExit).unwrap();
-                        last.push(LastStageState::AppExit).unwrap();
-                    }
-                    _ => unreachable_release!("State is interrupted"),
+    let elapsed = world.resource::<Time>().elapsed_seconds_f64();
+    let mut app_state = world.resource_mut::<GlobalState>();
+    //When global state is changed. Otherwise last transition is just forgotten.
+    if let Some(transition) = app_state.apply_change(elapsed) {
+        let hooks = app_state.hooks().collect::<Vec<_>>();
+        world.resource_scope(|world, callbacks: Mut<StateCallbacks>| {
+            for key in hooks {
+                for callback in callbacks.0.get(&key).into_iter().flatten() {
+                    callback(world, &transition);
                 }
             }
-            //General state shifting.
-            else {
-                match change_way {
-                    //Replace major to major.
-                    StateChangeWay::Replace => match *state {
-                        AppState::MainMenu => {
-                            first.replace(FirstStageState::MainMenu).unwrap();
-                            pre_update.replace(PreUpdateStageState::MainMenu).unwrap();
-                            update.replace(UpdateStageState::MainMenu).unwrap();
-                            post_update.replace(PostUpdateStageState::MainMenu).unwrap();
-                            last.replace(LastStageState::MainMenu).unwrap();
-                        }
-                        AppState::InGame => {
-                            first.replace(FirstStageState::InGame).unwrap();
-                            pre_update.replace(PreUpdateStageState::InGame).unwrap();
-                            update.replace(UpdateStageState::InGame).unwrap();
-                            post_update.replace(PostUpdateStageState::InGame).unwrap();
-                            last.replace(LastStageState::InGame).unwrap();
-                        }
-                    },
-                    //Push minor state.
-                    // StateChangeWay::Push => match *state {
-                    //     AppState::MainMenu(Some(m)) => {
-                    //         first.push(FirstStageState::MainMenu(Some(m))).unwrap();
-                    //         pre_update
-                    //             .push(PreUpdateStageState::MainMenu(Some(m)))
-                    //             .unwrap();
-                    //         update.push(UpdateStageState::MainMenu(Some(m))).unwrap();
-                    //         post_update
-                    //             .push(PostUpdateStageState::MainMenu(Some(m)))
-                    //             .unwrap();
-                    //         last.push(LastStageState::MainMenu(Some(m))).unwrap();
-                    //     }
-                    //     AppState::InGame(Some(i)) => {
-                    //         first.push(FirstStageState::InGame(Some(i))).unwrap();
-                    //         pre_update
-                    //             .push(PreUpdateStageState::InGame(Some(i)))
-                    //             .unwrap();
-                    //         update.push(UpdateStageState::InGame(Some(i))).unwrap();
-                    //         post_update
-                    //             .push(PostUpdateStageState::InGame(Some(i)))
-                    //             .unwrap();
-                    //         last.push(LastStageState::InGame(Some(i))).unwrap();
-                    //     }
-                    //     _ => unreachable_release!("State is interrupted"),
-                    // },
-                    //Pop minor or exit state.
-                    StateChangeWay::Pop => {
-                        first.pop().unwrap();
-                        pre_update.pop().unwrap();
-                        update.pop().unwrap();
-                        post_update.pop().unwrap();
-                        last.pop().unwrap();
-                    }
-                    _ => unreachable_release!("State is interrupted"),
-                };
-            }
         });
-
+        world.send_event(transition);
         clear_state(world, clear_system_state);
-    };
+    }
+}
+
+///Leaves trace of every state change.
+fn log_transition(mut events: EventReader<StateTransition>) {
+    for transition in events.iter() {
+        debug!(
+            "{:?} {:?} -> {:?}",
+            transition.way, transition.from, transition.to
+        );
+    }
 }
 
 type ClearStateSystemState<'w, 's> = SystemState<(
     Commands<'w, 's>,
-    Query<'w, 's, (Entity, &'w StateMark)>,
+    Query<'w, 's, (Entity, &'w StateMark, Option<&'w mut Visibility>)>,
     Res<'w, GlobalState>,
 )>;
 
@@ -413,12 +740,52 @@ type ClearStateSystemState<'w, 's> = SystemState<(
 fn clear_state(world: &mut World, system_state: &mut ClearStateSystemState) {
     let (mut commands, mut despawn_entities_query, app_state) = system_state.get_mut(world);
     let app_state = app_state.into_inner();
-    for (entity, state_mark) in despawn_entities_query.iter_mut() {
+    for (entity, state_mark, visibility) in despawn_entities_query.iter_mut() {
         if app_state.should_clear(state_mark) {
             //Also despawn childs.
             commands.entity(entity).despawn_recursive();
+        } else if let (true, Some(mut visibility)) = (state_mark.is_hidden(), visibility) {
+            let visible = !app_state.is_covered(state_mark);
+            if visibility.is_visible != visible {
+                visibility.is_visible = visible;
+            }
         }
     }
     //Applying commands to world immediately.
     system_state.apply(world);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejected_requests() {
+        let mut global = GlobalState::new(AppState::InGame(None));
+        assert!(matches!(
+            global.replace(AppState::InGame(None)),
+            Err(StateError::SameState)
+        ));
+        assert!(matches!(
+            global.replace_faded(AppState::InGame(None), Fade::default()),
+            Err(StateError::SameState)
+        ));
+        assert!(matches!(global.pop(), Err(StateError::NothingToPop)));
+
+        //Requests are checked against stack after queued ones.
+        global.push(InGameState::Pause).unwrap();
+        global.pop().unwrap();
+        assert!(matches!(global.pop(), Err(StateError::NothingToPop)));
+        global.push_exit().unwrap();
+        assert!(matches!(global.push_exit(), Err(StateError::NoSpace)));
+
+        //Nothing else is taken while fading.
+        global
+            .replace_faded(AppState::MainMenu(None), Fade::default())
+            .unwrap();
+        assert!(matches!(
+            global.replace(AppState::InGame(None)),
+            Err(StateError::AlreadyTransitioning)
+        ));
+    }
+}
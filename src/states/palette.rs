@@ -0,0 +1,268 @@
+use crate::{
+    asset::*,
+    controls::InputAction,
+    layout::{Anchor, Anchored},
+    physics::collider::{Collider, Shape},
+    states::{in_game::Selection, *},
+    theme::{TextColor, Theme},
+    ui::*,
+};
+
+use bevy::prelude::*;
+
+///Keys that pick slot of same number. They are fixed, since slots follow them.
+const SLOT_KEYS: [KeyCode; 9] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+];
+
+///Something player can build.
+#[derive(Clone)]
+pub struct Buildable {
+    ///English name, translated when shown.
+    pub name: &'static str,
+    pub meshes: Vec<Handle<Mesh>>,
+    pub material: Handle<StandardMaterial>,
+    pub collider: Collider,
+    ///Price shown in HUD. Placeholder, as placing doesn't charge it yet.
+    pub cost: u32,
+}
+
+///Every buildable, and which one ghost shows.
+#[derive(Resource)]
+pub struct Palette {
+    items: Vec<Buildable>,
+    selected: usize,
+}
+
+impl Palette {
+    pub fn new(meshes: &Meshes, standard_materials: &StandardMaterials) -> Self {
+        let items = vec![
+            Buildable {
+                name: "Gun tower",
+                meshes: vec![
+                    meshes[MESH_WEAPON][GUN_TOWER_0_BASE].clone(),
+                    meshes[MESH_WEAPON][GUN_TOWER_0_TOWER].clone(),
+                    meshes[MESH_WEAPON][GUN_TOWER_0_GUN].clone(),
+                ],
+                material: standard_materials[S_MAT_BUILT_IN][WHITE].clone(),
+                collider: Collider::from_shape(Shape::CutSphere {
+                    radius: 2.5,
+                    cut: 0.5,
+                }),
+                cost: 100,
+            },
+            Buildable {
+                name: "Block",
+                meshes: vec![meshes[MESH_BUILT_IN][CUBE].clone()],
+                material: standard_materials[S_MAT_BUILT_IN][ROCK].clone(),
+                collider: Collider::from_shape(Shape::Sphere { radius: 0.5 }),
+                cost: 10,
+            },
+        ];
+        Self { items, selected: 0 }
+    }
+
+    pub fn items(&self) -> &[Buildable] {
+        &self.items
+    }
+
+    pub fn selected(&self) -> &Buildable {
+        &self.items[self.selected]
+    }
+
+    pub fn selected_index(&self) -> usize {
+        self.selected
+    }
+
+    ///Selects item at index, if there is one.
+    pub fn select(&mut self, index: usize) {
+        if index < self.items.len() {
+            self.selected = index;
+        }
+    }
+
+    ///Index of item next to selected one, wrapping around.
+    pub fn cycled(&self, step: isize) -> usize {
+        let len = self.items.len() as isize;
+        (self.selected as isize + step).rem_euclid(len) as usize
+    }
+}
+
+///Slot of hotbar that shows item of palette.
+#[derive(Component)]
+struct HotbarSlot(usize);
+
+///Batch setup of build palette. Palette itself is made by in game setup.
+/// - Number keys pick slot, and next and previous actions go through them.
+/// - Ghost takes look and collider of selected item.
+pub struct PalettePlugin;
+
+impl Plugin for PalettePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set_to_stage(
+            CoreStage::PostUpdate,
+            on_enter(AppState::InGame(None)).with_system(setup),
+        )
+        .add_system_set_to_stage(
+            CoreStage::Update,
+            on_update(AppState::InGame(None))
+                .with_system(select)
+                .with_system(swap_ghost.after(select))
+                .with_system(hotbar.after(select)),
+        );
+    }
+}
+
+///Spawns hotbar at bottom of screen. Palette is inserted by then.
+fn setup(
+    mut commands: Commands,
+    state: Res<GlobalState>,
+    fonts: Res<Fonts>,
+    theme: Res<Theme>,
+    palette: Res<Palette>,
+) {
+    commands
+        .spawn((
+            NodeBundle::default(),
+            Anchored::new(Anchor::Bottom, Vec2::new(0.0, -8.0)),
+            state.mark(),
+        ))
+        .with_children(|parent| {
+            for (index, item) in palette.items().iter().enumerate() {
+                parent
+                    .spawn((
+                        NodeBundle {
+                            style: Style {
+                                size: Size::new(Val::Px(110.0), Val::Px(56.0)),
+                                margin: UiRect::horizontal(Val::Px(4.0)),
+                                flex_direction: FlexDirection::ColumnReverse,
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            background_color: slot_color(&theme, index, &palette).into(),
+                            ..default()
+                        },
+                        HotbarSlot(index),
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(create_value_text(
+                            (index + 1).to_string(),
+                            &fonts,
+                            &theme,
+                            16.0,
+                            TextColor::Bright,
+                        ));
+                        parent.spawn(create_text(
+                            item.name,
+                            &fonts,
+                            &theme,
+                            16.0,
+                            TextColor::Bright,
+                        ));
+                    });
+            }
+        });
+}
+
+fn slot_color(theme: &Theme, index: usize, palette: &Palette) -> Color {
+    match index == palette.selected_index() {
+        true => theme.button_hover,
+        false => theme.button,
+    }
+}
+
+///Changes selection by number keys and next or previous actions.
+fn select(
+    mut palette: ResMut<Palette>,
+    keys: Res<Input<KeyCode>>,
+    actions: Res<Input<InputAction>>,
+) {
+    let step = actions.just_pressed(InputAction::NextBuild) as isize
+        - actions.just_pressed(InputAction::PreviousBuild) as isize;
+    let index = match SLOT_KEYS.iter().position(|key| keys.just_pressed(*key)) {
+        Some(index) => index,
+        None => palette.cycled(step),
+    };
+    //Keep change detection quiet, since ghost is respawned on change.
+    if index != palette.selected_index() && index < palette.items().len() {
+        palette.select(index);
+    }
+}
+
+///Makes ghost, and so what is placed next, the selected item.
+fn swap_ghost(
+    mut commands: Commands,
+    palette: Res<Palette>,
+    mut ghost: Query<(Entity, &mut Selection)>,
+) {
+    if !palette.is_changed() {
+        return;
+    }
+    for (entity, mut selection) in ghost.iter_mut() {
+        selection.set(palette.selected().clone());
+        commands.entity(entity).despawn_descendants();
+        commands.entity(entity).with_children(|parent| {
+            for bundle in selection.create_transparent() {
+                parent.spawn(bundle);
+            }
+        });
+    }
+}
+
+///Highlights slot of selected item.
+fn hotbar(
+    palette: Res<Palette>,
+    theme: Res<Theme>,
+    mut slots: Query<(&HotbarSlot, &mut BackgroundColor)>,
+) {
+    if !palette.is_changed() && !theme.is_changed() {
+        return;
+    }
+    for (slot, mut color) in slots.iter_mut() {
+        color.0 = slot_color(&theme, slot.0, &palette);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn palette(len: usize) -> Palette {
+        let item = Buildable {
+            name: "Item",
+            meshes: Vec::new(),
+            material: Handle::default(),
+            collider: Collider::from_shape(Shape::Sphere { radius: 0.5 }),
+            cost: 0,
+        };
+        Palette {
+            items: vec![item; len],
+            selected: 0,
+        }
+    }
+
+    #[test]
+    fn selection() {
+        let mut palette = palette(2);
+        palette.select(1);
+        assert_eq!(palette.selected_index(), 1);
+        //Slot without item keeps selection.
+        palette.select(8);
+        assert_eq!(palette.selected_index(), 1);
+
+        //Wraps around both ways.
+        assert_eq!(palette.cycled(1), 0);
+        palette.select(0);
+        assert_eq!(palette.cycled(-1), 1);
+        assert_eq!(palette.cycled(4), 0);
+    }
+}
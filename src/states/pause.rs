@@ -0,0 +1,86 @@
+use crate::{
+    asset::Fonts,
+    controls::InputAction,
+    func::*,
+    states::{fade::Fade, *},
+    theme::{TextColor, Theme},
+    ui::*,
+};
+
+use bevy::{prelude::*, window::WindowCloseRequested};
+
+///Batch setup for pause menu, which is stacked on In game.
+/// - Every gameplay system only runs on update of In game, so pushing pause freezes them and
+///   every timer they tick.
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set_to_stage(
+            CoreStage::PreUpdate,
+            on_enter(AppState::InGame(Some(InGameState::Pause))).with_system(setup),
+        )
+        .add_system_set_to_stage(
+            CoreStage::Update,
+            on_update(AppState::InGame(Some(InGameState::Pause))).with_system(pop_requested),
+        )
+        .add_system_set_to_stage(
+            CoreStage::PostUpdate,
+            on_update(AppState::InGame(Some(InGameState::Pause)))
+                .with_system(navigate::<HierarchyMark<1>>.label(NavigationSystem)),
+        );
+    }
+}
+
+///Go to pause when requested, or to exit state when window is about to close.
+pub fn pause_requested(
+    closed: EventReader<WindowCloseRequested>,
+    mut state: ResMut<GlobalState>,
+    actions: Res<Input<InputAction>>,
+) {
+    if !closed.is_empty() {
+        let _ = state.push_exit();
+    } else if actions.just_pressed(InputAction::Pause) {
+        let _ = state.push(InGameState::Pause);
+    }
+}
+
+///Setup pause popup.
+fn setup(mut commands: Commands, state: Res<GlobalState>, fonts: Res<Fonts>, theme: Res<Theme>) {
+    let buttons: [(&str, fn(ResMut<GlobalState>)); 4] = [
+        (RESUME_TEXT, |mut g| {
+            let _ = g.pop();
+        }),
+        (SETTINGS_TEXT, |mut g| {
+            let _ = g.push(InGameState::Settings);
+        }),
+        (MAIN_MENU_TEXT, |mut g| {
+            let _ = g.replace_faded(AppState::MainMenu(None), Fade::default());
+        }),
+        (QUIT_TEXT, |mut g| {
+            let _ = g.push_exit();
+        }),
+    ];
+    //Pause is always depth 1. Hides below screens pushed on it.
+    commands
+        .spawn((
+            create_popup(&theme, 70.0),
+            state.mark().until_exit_of(1).hidden(),
+        ))
+        .with_children(|parent| {
+            parent.spawn(create_text(
+                PAUSE_TEXT,
+                &fonts,
+                &theme,
+                40.0,
+                TextColor::Dark,
+            ));
+            for (text, func) in buttons {
+                parent
+                    .spawn((create_button(&theme), Action::new(func), HierarchyMark::<1>))
+                    .with_children(|parent| {
+                        parent.spawn(create_text(text, &fonts, &theme, 30.0, TextColor::Bright));
+                    });
+            }
+        });
+}
@@ -0,0 +1,273 @@
+use crate::{
+    asset::Fonts,
+    func::*,
+    locale::{cycle_language, language_name, Locale},
+    settings::{ConfigDir, Settings, SETTINGS_FILE},
+    states::*,
+    theme::{TextColor, Theme},
+    ui::*,
+};
+
+use bevy::prelude::*;
+
+///Settings screen is same whether it is opened from main menu or pause.
+const SETTINGS_STATES: [AppState; 2] = [
+    AppState::MainMenu(Some(MainMenuState::Settings)),
+    AppState::InGame(Some(InGameState::Settings)),
+];
+
+///Batch setup for settings screen, which is stacked on main menu or pause.
+/// - Changes apply right away and are saved when screen is left.
+pub struct SettingsMenuPlugin;
+
+impl Plugin for SettingsMenuPlugin {
+    fn build(&self, app: &mut App) {
+        for state in SETTINGS_STATES {
+            app.add_system_set_to_stage(CoreStage::PreUpdate, on_enter(state).with_system(setup))
+                .add_system_set_to_stage(
+                    CoreStage::Update,
+                    on_update(state)
+                        .with_system(settings_text)
+                        .with_system(pop_requested),
+                )
+                .add_system_set_to_stage(
+                    CoreStage::PostUpdate,
+                    on_update(state)
+                        .with_system(navigate::<SettingsButton>.label(NavigationSystem)),
+                )
+                //Exit popup could end app right after.
+                .add_system_set_to_stage(CoreStage::PreUpdate, on_pause(state).with_system(save))
+                .add_system_set_to_stage(CoreStage::PreUpdate, on_exit(state).with_system(save));
+        }
+    }
+}
+
+///Button of settings screen, as its depth differs between main menu and pause.
+#[derive(Component)]
+pub struct SettingsButton;
+
+///Text that shows value of a setting.
+#[derive(Component)]
+struct SettingText(fn(&Settings, &Locale) -> String);
+
+///Adds delta and snaps to multiple of it, so float errors don't pile up.
+fn step(value: f32, delta: f32, min: f32, max: f32) -> f32 {
+    (((value + delta) / delta).round() * delta).clamp(min, max)
+}
+
+///Arrow action that changes settings.
+fn change(f: fn(&mut Settings)) -> Action {
+    Action::new(move |mut settings: ResMut<Settings>| f(&mut settings))
+}
+
+///Setup settings popup.
+fn setup(
+    mut commands: Commands,
+    state: Res<GlobalState>,
+    fonts: Res<Fonts>,
+    theme: Res<Theme>,
+    settings: Res<Settings>,
+    locale: Res<Locale>,
+) {
+    //Label, value, and what left and right arrows do.
+    type Row = (
+        &'static str,
+        fn(&Settings, &Locale) -> String,
+        fn(&mut Settings),
+        fn(&mut Settings),
+    );
+    let rows: [Row; 9] = [
+        (
+            MOUSE_SENSITIVITY_TEXT,
+            |s, _| format!("{:.2}", s.mouse_sensitivity),
+            |s| s.mouse_sensitivity = step(s.mouse_sensitivity, -0.01, 0.01, 1.),
+            |s| s.mouse_sensitivity = step(s.mouse_sensitivity, 0.01, 0.01, 1.),
+        ),
+        (
+            STICK_SENSITIVITY_TEXT,
+            |s, _| format!("{:.0}", s.stick_sensitivity),
+            |s| s.stick_sensitivity = step(s.stick_sensitivity, -10., 10., 360.),
+            |s| s.stick_sensitivity = step(s.stick_sensitivity, 10., 10., 360.),
+        ),
+        (
+            STICK_DEAD_ZONE_TEXT,
+            |s, _| format!("{:.2}", s.stick_dead_zone),
+            |s| s.stick_dead_zone = step(s.stick_dead_zone, -0.05, 0., 0.5),
+            |s| s.stick_dead_zone = step(s.stick_dead_zone, 0.05, 0., 0.5),
+        ),
+        (
+            MOVE_SPEED_TEXT,
+            |s, _| format!("{:.0}", s.move_speed),
+            |s| s.move_speed = step(s.move_speed, -1., 1., 50.),
+            |s| s.move_speed = step(s.move_speed, 1., 1., 50.),
+        ),
+        (
+            LONG_PRESS_TEXT,
+            |s, _| format!("{:.1}s", s.long_press_delay),
+            |s| s.long_press_delay = step(s.long_press_delay, -0.1, 0.1, 3.),
+            |s| s.long_press_delay = step(s.long_press_delay, 0.1, 0.1, 3.),
+        ),
+        (
+            DISPLAY_MODE_TEXT,
+            |s, l| l.get(&s.display_mode.to_string()).to_owned(),
+            |s| s.display_mode = s.display_mode.cycle(-1),
+            |s| s.display_mode = s.display_mode.cycle(1),
+        ),
+        (
+            VSYNC_TEXT,
+            |s, l| l.get(if s.vsync { ON_TEXT } else { OFF_TEXT }).to_owned(),
+            |s| s.vsync = !s.vsync,
+            |s| s.vsync = !s.vsync,
+        ),
+        (
+            UI_SCALE_TEXT,
+            |s, _| format!("{:.1}x", s.ui_scale),
+            |s| s.ui_scale = step(s.ui_scale, -0.1, 0.5, 2.),
+            |s| s.ui_scale = step(s.ui_scale, 0.1, 0.5, 2.),
+        ),
+        (
+            LANGUAGE_TEXT,
+            //Each language is named in itself, so it can be found from any other.
+            |s, _| language_name(&s.language).to_owned(),
+            |s| s.language = cycle_language(&s.language, -1).to_owned(),
+            |s| s.language = cycle_language(&s.language, 1).to_owned(),
+        ),
+    ];
+    let arrow = || ButtonBundle {
+        style: Style {
+            size: Size::new(Val::Px(40.0), Val::Px(40.0)),
+            ..create_button(&theme).style
+        },
+        ..create_button(&theme)
+    };
+    commands
+        .spawn((create_popup(&theme, 90.0), state.mark()))
+        .with_children(|parent| {
+            parent.spawn(create_text(
+                SETTINGS_TEXT,
+                &fonts,
+                &theme,
+                40.0,
+                TextColor::Dark,
+            ));
+            for (label, value, left, right) in rows {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            size: Size::new(Val::Percent(90.0), Val::Px(42.0)),
+                            justify_content: JustifyContent::SpaceBetween,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(create_text(label, &fonts, &theme, 20.0, TextColor::Dark));
+                        parent
+                            .spawn((arrow(), change(left), SettingsButton))
+                            .with_children(|parent| {
+                                parent.spawn(create_text(
+                                    "<",
+                                    &fonts,
+                                    &theme,
+                                    20.0,
+                                    TextColor::Bright,
+                                ));
+                            });
+                        parent.spawn((
+                            create_value_text(
+                                value(&settings, &locale),
+                                &fonts,
+                                &theme,
+                                20.0,
+                                TextColor::Dark,
+                            ),
+                            SettingText(value),
+                        ));
+                        parent
+                            .spawn((arrow(), change(right), SettingsButton))
+                            .with_children(|parent| {
+                                parent.spawn(create_text(
+                                    ">",
+                                    &fonts,
+                                    &theme,
+                                    20.0,
+                                    TextColor::Bright,
+                                ));
+                            });
+                    });
+            }
+            parent
+                .spawn((
+                    create_button(&theme),
+                    Action::new(|mut g: ResMut<GlobalState>| {
+                        let _ = match g.current() {
+                            AppState::MainMenu(_) => g.push(MainMenuState::Controls),
+                            _ => g.push(InGameState::Controls),
+                        };
+                    }),
+                    SettingsButton,
+                ))
+                .with_children(|parent| {
+                    parent.spawn(create_text(
+                        CONTROLS_TEXT,
+                        &fonts,
+                        &theme,
+                        30.0,
+                        TextColor::Bright,
+                    ));
+                });
+            parent
+                .spawn((
+                    create_button(&theme),
+                    Action::new(|mut g: ResMut<GlobalState>| {
+                        let _ = g.back();
+                    }),
+                    SettingsButton,
+                ))
+                .with_children(|parent| {
+                    parent.spawn(create_text(
+                        BACK_TEXT,
+                        &fonts,
+                        &theme,
+                        30.0,
+                        TextColor::Bright,
+                    ));
+                });
+        });
+}
+
+///Keeps value texts up to date.
+fn settings_text(
+    settings: Res<Settings>,
+    locale: Res<Locale>,
+    mut query: Query<(&mut Text, &SettingText)>,
+) {
+    if settings.is_changed() || locale.is_changed() {
+        for (mut text, value) in query.iter_mut() {
+            text.sections[0].value = (value.0)(&settings, &locale);
+        }
+    }
+}
+
+fn save(settings: Res<Settings>, config: Res<ConfigDir>) {
+    config.save(SETTINGS_FILE, &*settings);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn step_snaps_and_clamps() {
+        //Float errors of repeated steps don't pile up.
+        let mut value = 0.;
+        for _ in 0..7 {
+            value = step(value, 0.1, 0., 1.);
+        }
+        assert_eq!(value, 0.7);
+        assert_eq!(step(0.33, 0.1, 0., 1.), 0.4);
+        assert_eq!(step(1., 0.1, 0., 1.), 1.);
+        assert_eq!(step(10., -10., 10., 360.), 10.);
+    }
+}
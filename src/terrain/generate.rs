@@ -0,0 +1,238 @@
+use crate::terrain::{Terrain, Voxel};
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+
+///Seed of map to generate when entering game. None is flat map.
+#[derive(Resource, Default)]
+pub struct MapSeed(pub Option<u64>);
+
+impl MapSeed {
+    ///Switches between flat map and freshly seeded one.
+    pub fn cycle(&mut self) {
+        self.0 = match self.0 {
+            Some(_) => None,
+            None => Some(Self::random()),
+        }
+    }
+
+    ///Short seed from clock so it is readable on screen.
+    fn random() -> u64 {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+        SplitMix64(nanos).next_u64() % 100_000
+    }
+}
+
+///What generator placed other than terrain.
+pub struct MapLayout {
+    ///Cell where enemies come from.
+    pub spawn: IVec3,
+    ///Cell where enemies head to.
+    pub goal: IVec3,
+    ///Center and radius of rocks.
+    pub rocks: Vec<(Vec3, f32)>,
+}
+
+///Deterministic map from seed. Same seed always gives same layout.
+pub struct MapGenerator {
+    seed: u64,
+    ///Height of the lowest ground.
+    base_height: i32,
+    ///How much hills rise above base.
+    amplitude: f32,
+    ///Cells per noise period of the first octave.
+    period: f32,
+    ///Step of terraces, which makes cliffs.
+    terrace: i32,
+    rock_count: usize,
+}
+
+impl MapGenerator {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            base_height: 2,
+            amplitude: 12.,
+            period: 24.,
+            terrace: 2,
+            rock_count: 16,
+        }
+    }
+
+    ///Fills terrain and returns the rest of layout.
+    pub fn generate(&self, terrain: &mut Terrain) -> MapLayout {
+        let min = terrain.origin();
+        let max = terrain.origin() + terrain.size() - 1;
+        let mut rng = SplitMix64(self.seed);
+
+        //Heightfield
+        let mut heights = Vec::with_capacity(((max.x - min.x + 1) * (max.z - min.z + 1)) as usize);
+        for z in min.z..=max.z {
+            for x in min.x..=max.x {
+                let height = self.height(x, z).min(max.y - min.y);
+                heights.push(height);
+                for y in 0..height {
+                    let voxel = if y == height - 1 {
+                        Voxel::Grass
+                    } else if y < height - 3 {
+                        Voxel::Rock
+                    } else {
+                        Voxel::Dirt
+                    };
+                    terrain.set(IVec3::new(x, min.y + y, z), voxel);
+                }
+            }
+        }
+        let surface = |x: i32, z: i32| {
+            let index = (x - min.x) + (z - min.z) * (max.x - min.x + 1);
+            IVec3::new(x, min.y + heights[index as usize], z)
+        };
+
+        //Spawn and goal on opposite sides.
+        let margin = 2;
+        let spawn = surface(
+            min.x + margin,
+            rng.range_i32(min.z + margin, max.z - margin),
+        );
+        let goal = surface(
+            max.x - margin,
+            rng.range_i32(min.z + margin, max.z - margin),
+        );
+
+        //Rocks, but keep away from spawn and goal.
+        let mut rocks = Vec::with_capacity(self.rock_count);
+        while rocks.len() < self.rock_count {
+            let cell = surface(
+                rng.range_i32(min.x + margin, max.x - margin),
+                rng.range_i32(min.z + margin, max.z - margin),
+            );
+            if (cell - spawn).abs().max_element() < 6 || (cell - goal).abs().max_element() < 6 {
+                continue;
+            }
+            let radius = 0.8 + rng.next_f32() * 0.8;
+            //Half buried.
+            rocks.push((cell.as_vec3() + Vec3::Y * (radius * 0.5 - 0.5), radius));
+        }
+
+        MapLayout { spawn, goal, rocks }
+    }
+
+    ///Terraced fractal noise at column.
+    fn height(&self, x: i32, z: i32) -> i32 {
+        let mut sum = 0.;
+        let mut weight = 1.;
+        let mut total = 0.;
+        let mut frequency = self.period.recip();
+        for octave in 0..4 {
+            sum += weight
+                * value_noise(
+                    self.seed.wrapping_add(octave),
+                    x as f32 * frequency,
+                    z as f32 * frequency,
+                );
+            total += weight;
+            weight *= 0.5;
+            frequency *= 2.;
+        }
+        let height = self.base_height + (sum / total * self.amplitude) as i32;
+        height / self.terrace * self.terrace
+    }
+}
+
+///Smoothly interpolated lattice noise in 0..1.
+fn value_noise(seed: u64, x: f32, z: f32) -> f32 {
+    let (x0, z0) = (x.floor(), z.floor());
+    let (fx, fz) = (x - x0, z - z0);
+    let (sx, sz) = (fx * fx * (3. - 2. * fx), fz * fz * (3. - 2. * fz));
+    let (x0, z0) = (x0 as i32, z0 as i32);
+    let a = lattice(seed, x0, z0);
+    let b = lattice(seed, x0 + 1, z0);
+    let c = lattice(seed, x0, z0 + 1);
+    let d = lattice(seed, x0 + 1, z0 + 1);
+    let top = a + (b - a) * sx;
+    let bottom = c + (d - c) * sx;
+    top + (bottom - top) * sz
+}
+
+///Random value in 0..1 fixed to lattice point.
+fn lattice(seed: u64, x: i32, z: i32) -> f32 {
+    let mut rng = SplitMix64(
+        seed ^ (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+            ^ (z as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F),
+    );
+    rng.next_f32()
+}
+
+///Small and fast pseudo random generator. Not for security.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    ///Uniform in 0..1.
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    ///Uniform in min..=max.
+    fn range_i32(&mut self, min: i32, max: i32) -> i32 {
+        min + (self.next_u64() % (max - min + 1) as u64) as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::physics::aabb::AABB;
+
+    fn generate(seed: u64) -> (Terrain, MapLayout) {
+        let mut terrain = Terrain::new(
+            AABB::new(Vec3::new(-15.5, -0.5, -15.5), Vec3::new(15.5, 30.5, 15.5)),
+            Handle::default(),
+        );
+        let layout = MapGenerator::new(seed).generate(&mut terrain);
+        (terrain, layout)
+    }
+
+    ///Every cell of terrain, in the same order for terrains of same size.
+    fn voxels(terrain: &Terrain) -> Vec<Voxel> {
+        let (min, max) = (terrain.origin(), terrain.origin() + terrain.size() - 1);
+        (min.z..=max.z)
+            .flat_map(|z| {
+                (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| IVec3::new(x, y, z)))
+            })
+            .map(|cell| terrain.get(cell))
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_same_map() {
+        let (terrain, layout) = generate(42);
+        let (again, layout_again) = generate(42);
+        assert_eq!(voxels(&terrain), voxels(&again));
+        assert_eq!(layout.rocks, layout_again.rocks);
+        assert_eq!(
+            (layout.spawn, layout.goal),
+            (layout_again.spawn, layout_again.goal)
+        );
+        assert!(voxels(&terrain).iter().any(|voxel| voxel.is_solid()));
+    }
+
+    #[test]
+    fn other_seed_other_map() {
+        let (terrain, layout) = generate(42);
+        let (other, other_layout) = generate(7);
+        assert_ne!(voxels(&terrain), voxels(&other));
+        assert_ne!(layout.rocks, other_layout.rocks);
+    }
+}
@@ -0,0 +1,153 @@
+use crate::terrain::{Terrain, Voxel, CHUNK_SIZE};
+
+use bevy::{
+    prelude::*,
+    render::{mesh::Indices, render_resource::PrimitiveTopology},
+};
+
+///Builds mesh of chunk starting at min cell. Faces of same voxel are merged into larger quads.
+/// - None if chunk has no visible face.
+pub fn greedy_mesh(terrain: &Terrain, min: IVec3) -> Option<Mesh> {
+    let mut positions = Vec::<[f32; 3]>::new();
+    let mut normals = Vec::<[f32; 3]>::new();
+    let mut uvs = Vec::<[f32; 2]>::new();
+    let mut colors = Vec::<[f32; 4]>::new();
+    let mut indices = Vec::<u32>::new();
+    let mut mask = vec![Voxel::Air; (CHUNK_SIZE * CHUNK_SIZE) as usize];
+
+    //Sweep every slice of every axis in both directions.
+    for axis in 0..3 {
+        //u, v and axis is right-handed, so quad in order of (u, v) faces positive axis.
+        let u = (axis + 1) % 3;
+        let v = (axis + 2) % 3;
+        for sign in [1, -1] {
+            let mut normal = IVec3::ZERO;
+            normal[axis] = sign;
+            for slice in 0..CHUNK_SIZE {
+                //Mask of faces visible in this slice.
+                for j in 0..CHUNK_SIZE {
+                    for i in 0..CHUNK_SIZE {
+                        let mut local = IVec3::ZERO;
+                        local[axis] = slice;
+                        local[u] = i;
+                        local[v] = j;
+                        let voxel = terrain.get(min + local);
+                        mask[(i + j * CHUNK_SIZE) as usize] =
+                            if voxel.is_solid() && !terrain.get(min + local + normal).is_solid() {
+                                voxel
+                            } else {
+                                Voxel::Air
+                            };
+                    }
+                }
+                //Consume mask into as large rectangles as possible.
+                for j in 0..CHUNK_SIZE {
+                    let mut i = 0;
+                    while i < CHUNK_SIZE {
+                        let voxel = mask[(i + j * CHUNK_SIZE) as usize];
+                        if !voxel.is_solid() {
+                            i += 1;
+                            continue;
+                        }
+                        let mut width = 1;
+                        while i + width < CHUNK_SIZE
+                            && mask[(i + width + j * CHUNK_SIZE) as usize] == voxel
+                        {
+                            width += 1;
+                        }
+                        let mut height = 1;
+                        'grow: while j + height < CHUNK_SIZE {
+                            for k in 0..width {
+                                if mask[(i + k + (j + height) * CHUNK_SIZE) as usize] != voxel {
+                                    break 'grow;
+                                }
+                            }
+                            height += 1;
+                        }
+                        for l in 0..height {
+                            for k in 0..width {
+                                mask[(i + k + (j + l) * CHUNK_SIZE) as usize] = Voxel::Air;
+                            }
+                        }
+
+                        //Emit quad.
+                        let mut base = min.as_vec3();
+                        base[axis] += slice as f32 + 0.5 * sign as f32;
+                        base[u] += i as f32 - 0.5;
+                        base[v] += j as f32 - 0.5;
+                        let mut du = Vec3::ZERO;
+                        du[u] = width as f32;
+                        let mut dv = Vec3::ZERO;
+                        dv[v] = height as f32;
+                        let start = positions.len() as u32;
+                        for (corner, uv) in [
+                            (base, [0., 0.]),
+                            (base + du, [width as f32, 0.]),
+                            (base + du + dv, [width as f32, height as f32]),
+                            (base + dv, [0., height as f32]),
+                        ] {
+                            positions.push(corner.to_array());
+                            normals.push(normal.as_vec3().to_array());
+                            uvs.push(uv);
+                            colors.push(voxel.color());
+                        }
+                        if sign > 0 {
+                            indices.extend([
+                                start,
+                                start + 1,
+                                start + 2,
+                                start,
+                                start + 2,
+                                start + 3,
+                            ]);
+                        } else {
+                            indices.extend([
+                                start,
+                                start + 2,
+                                start + 1,
+                                start,
+                                start + 3,
+                                start + 2,
+                            ]);
+                        }
+                        i += width;
+                    }
+                }
+            }
+        }
+    }
+
+    if indices.is_empty() {
+        return None;
+    }
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    Some(mesh)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::physics::aabb::AABB;
+
+    #[test]
+    fn flat_slab_is_six_quads() {
+        let mut terrain = Terrain::new(
+            AABB::new(Vec3::splat(-0.5), Vec3::splat(31.5)),
+            Handle::default(),
+        );
+        terrain.fill(IVec3::ZERO, IVec3::new(15, 0, 15), Voxel::Grass);
+        let mesh = greedy_mesh(&terrain, IVec3::ZERO).unwrap();
+        //Top, bottom and four sides, two triangles each.
+        assert_eq!(mesh.count_vertices(), 6 * 4);
+        assert_eq!(mesh.indices().unwrap().len(), 6 * 6);
+
+        //Neighbor chunk has nothing to show.
+        assert!(greedy_mesh(&terrain, IVec3::new(16, 0, 0)).is_none());
+    }
+}
@@ -0,0 +1,436 @@
+pub mod generate;
+pub mod mesh;
+
+use crate::{
+    physics::{aabb::AABB, ray::Ray},
+    terrain::{generate::MapSeed, mesh::greedy_mesh},
+};
+
+use bevy::prelude::*;
+
+///Edge length of a chunk in cells.
+pub const CHUNK_SIZE: i32 = 16;
+
+///Batch setup of terrain.
+pub struct TerrainPlugin;
+
+impl Plugin for TerrainPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MapSeed>()
+            .add_system_to_stage(CoreStage::PostUpdate, mesh_terrain);
+    }
+}
+
+///Material of a single terrain cell.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default)]
+pub enum Voxel {
+    #[default]
+    Air,
+    Dirt,
+    Grass,
+    Rock,
+}
+
+impl Voxel {
+    pub fn is_solid(self) -> bool {
+        self != Voxel::Air
+    }
+
+    ///Vertex color of faces.
+    pub fn color(self) -> [f32; 4] {
+        match self {
+            Voxel::Air => [0., 0., 0., 0.],
+            Voxel::Dirt => [0.45, 0.32, 0.2, 1.],
+            Voxel::Grass => Color::SEA_GREEN.as_rgba_f32(),
+            Voxel::Rock => [0.5, 0.5, 0.52, 1.],
+        }
+    }
+}
+
+///Cube of cells that is meshed as a whole.
+struct Chunk {
+    voxels: Vec<Voxel>,
+    ///Whether mesh is outdated.
+    dirty: bool,
+    ///Entity that renders mesh of chunk.
+    entity: Option<Entity>,
+}
+
+impl Chunk {
+    fn new() -> Self {
+        Self {
+            voxels: vec![Voxel::Air; (CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as usize],
+            dirty: true,
+            entity: None,
+        }
+    }
+
+    fn index(local: IVec3) -> usize {
+        (local.x + CHUNK_SIZE * (local.y + CHUNK_SIZE * local.z)) as usize
+    }
+}
+
+///Information about where ray met solid cell.
+pub struct VoxelHit {
+    pub cell: IVec3,
+    ///Unit vector of face that ray entered.
+    pub normal: IVec3,
+    ///Distance
+    pub t: f32,
+}
+
+///Editable voxel terrain, one cell per unit like OccupancyGrid.
+/// - Cells are grouped into chunks, and only edited chunks are meshed again.
+/// - Cells outside of volume are always air.
+#[derive(Component)]
+pub struct Terrain {
+    ///Cell at minimum corner.
+    origin: IVec3,
+    size: IVec3,
+    ///Number of chunks along each axis.
+    chunks_size: IVec3,
+    chunks: Vec<Chunk>,
+    material: Handle<StandardMaterial>,
+}
+
+impl Terrain {
+    ///Covers every cell whose center is inside given bound.
+    pub fn new(aabb: AABB, material: Handle<StandardMaterial>) -> Self {
+        let origin = aabb.min().floor().as_ivec3() + 1;
+        let size = (aabb.max().ceil().as_ivec3() - origin).max(IVec3::ZERO);
+        let chunks_size = (size + CHUNK_SIZE - 1) / CHUNK_SIZE;
+        Self {
+            origin,
+            size,
+            chunks_size,
+            chunks: (0..chunks_size.x * chunks_size.y * chunks_size.z)
+                .map(|_| Chunk::new())
+                .collect(),
+            material,
+        }
+    }
+
+    ///Cell at minimum corner.
+    pub fn origin(&self) -> IVec3 {
+        self.origin
+    }
+
+    ///Number of cells along each axis.
+    pub fn size(&self) -> IVec3 {
+        self.size
+    }
+
+    ///Bound that covers every cell.
+    pub fn bound(&self) -> AABB {
+        AABB::new(
+            self.origin.as_vec3() - 0.5,
+            (self.origin + self.size).as_vec3() - 0.5,
+        )
+    }
+
+    pub fn contains(&self, cell: IVec3) -> bool {
+        let local = cell - self.origin;
+        local.cmpge(IVec3::ZERO).all() && local.cmplt(self.size).all()
+    }
+
+    ///Index of chunk and index of cell in chunk.
+    fn index(&self, cell: IVec3) -> Option<(usize, usize)> {
+        if !self.contains(cell) {
+            return None;
+        }
+        let local = cell - self.origin;
+        let chunk = local / CHUNK_SIZE;
+        Some((
+            (chunk.x + self.chunks_size.x * (chunk.y + self.chunks_size.y * chunk.z)) as usize,
+            Chunk::index(local % CHUNK_SIZE),
+        ))
+    }
+
+    ///Position of chunk at index, counted in chunks.
+    fn chunk_pos(&self, index: usize) -> IVec3 {
+        let index = index as i32;
+        IVec3::new(
+            index % self.chunks_size.x,
+            index / self.chunks_size.x % self.chunks_size.y,
+            index / (self.chunks_size.x * self.chunks_size.y),
+        )
+    }
+
+    ///Bound of solid cells of every chunk that has any, for spatial tree.
+    pub fn chunk_bounds(&self) -> impl Iterator<Item = AABB> + '_ {
+        self.chunks.iter().enumerate().filter_map(|(index, chunk)| {
+            let min = self.origin + self.chunk_pos(index) * CHUNK_SIZE;
+            let solid = chunk
+                .voxels
+                .iter()
+                .enumerate()
+                .filter(|(_, voxel)| voxel.is_solid());
+            let (low, high) = solid.fold(None, |bound, (i, _)| {
+                let i = i as i32;
+                let cell = min
+                    + IVec3::new(
+                        i % CHUNK_SIZE,
+                        i / CHUNK_SIZE % CHUNK_SIZE,
+                        i / (CHUNK_SIZE * CHUNK_SIZE),
+                    );
+                Some(match bound {
+                    Some((low, high)) => (cell.min(low), cell.max(high)),
+                    None => (cell, cell),
+                })
+            })?;
+            Some(AABB::new(low.as_vec3() - 0.5, high.as_vec3() + 0.5))
+        })
+    }
+
+    pub fn get(&self, cell: IVec3) -> Voxel {
+        match self.index(cell) {
+            Some((chunk, index)) => self.chunks[chunk].voxels[index],
+            None => Voxel::Air,
+        }
+    }
+
+    ///Return is whether cell is inside of volume.
+    pub fn set(&mut self, cell: IVec3, voxel: Voxel) -> bool {
+        match self.index(cell) {
+            Some((chunk, index)) => {
+                if self.chunks[chunk].voxels[index] != voxel {
+                    self.chunks[chunk].voxels[index] = voxel;
+                    self.chunks[chunk].dirty = true;
+                    //Faces of neighbor chunks could be revealed or hidden.
+                    let local = (cell - self.origin) % CHUNK_SIZE;
+                    for axis in 0..3 {
+                        let mut offset = IVec3::ZERO;
+                        if local[axis] == 0 {
+                            offset[axis] = -1;
+                        } else if local[axis] == CHUNK_SIZE - 1 {
+                            offset[axis] = 1;
+                        } else {
+                            continue;
+                        }
+                        if let Some((neighbor, _)) = self.index(cell + offset) {
+                            self.chunks[neighbor].dirty = true;
+                        }
+                    }
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    ///Sets every cell between min and max inclusively.
+    pub fn fill(&mut self, min: IVec3, max: IVec3, voxel: Voxel) {
+        for z in min.z..=max.z {
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    self.set(IVec3::new(x, y, z), voxel);
+                }
+            }
+        }
+    }
+
+    ///Height of ground surface right below point. Bottom of volume if there's no solid cell.
+    pub fn ground(&self, point: Vec3) -> f32 {
+        let cell = point.round().as_ivec3();
+        let mut y = cell.y.min(self.origin.y + self.size.y - 1);
+        while y >= self.origin.y {
+            if self.get(IVec3::new(cell.x, y, cell.z)).is_solid() {
+                return y as f32 + 0.5;
+            }
+            y -= 1;
+        }
+        self.origin.y as f32 - 0.5
+    }
+
+    ///Walks cells along ray until it meets solid one.
+    pub fn raycast(&self, ray: &Ray) -> Option<VoxelHit> {
+        let bound = self.bound();
+        let (t_min, t_max) = bound.intersects_ray_raw(ray)?;
+        let origin = ray.origin();
+        let dir = ray.dir();
+        let mut t = t_min.max(0.);
+        //Start cell. Clamp since point could be on bound line.
+        let mut cell = ray
+            .point(t)
+            .round()
+            .as_ivec3()
+            .clamp(self.origin, self.origin + self.size - 1);
+        //Face of entry. When ray starts inside, take the most dominant axis of ray.
+        let mut normal = if t_min > 0. {
+            bound.face(ray.point(t)).round().as_ivec3()
+        } else {
+            let abs = dir.abs();
+            if abs.x >= abs.y && abs.x >= abs.z {
+                IVec3::new(-dir.x.signum() as i32, 0, 0)
+            } else if abs.y >= abs.z {
+                IVec3::new(0, -dir.y.signum() as i32, 0)
+            } else {
+                IVec3::new(0, 0, -dir.z.signum() as i32)
+            }
+        };
+        let mut step = IVec3::ZERO;
+        let mut t_next = Vec3::splat(f32::INFINITY);
+        let mut t_delta = Vec3::splat(f32::INFINITY);
+        for axis in 0..3 {
+            if dir[axis] > 0. {
+                step[axis] = 1;
+            } else if dir[axis] < 0. {
+                step[axis] = -1;
+            } else {
+                continue;
+            }
+            let boundary = cell[axis] as f32 + 0.5 * step[axis] as f32;
+            t_next[axis] = (boundary - origin[axis]) / dir[axis];
+            t_delta[axis] = dir[axis].abs().recip();
+        }
+        loop {
+            if self.get(cell).is_solid() {
+                return Some(VoxelHit { cell, normal, t });
+            }
+            //Step to the nearest boundary.
+            let axis = if t_next.x <= t_next.y && t_next.x <= t_next.z {
+                0
+            } else if t_next.y <= t_next.z {
+                1
+            } else {
+                2
+            };
+            t = t_next[axis];
+            if t > t_max {
+                return None;
+            }
+            cell[axis] += step[axis];
+            normal = IVec3::ZERO;
+            normal[axis] = -step[axis];
+            t_next[axis] += t_delta[axis];
+            if !self.contains(cell) {
+                return None;
+            }
+        }
+    }
+}
+
+///Meshes dirty chunks again and spawns entities for new ones.
+fn mesh_terrain(
+    mut commands: Commands,
+    mut terrains: Query<(Entity, &mut Terrain)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    for (terrain_entity, mut terrain) in terrains.iter_mut() {
+        for chunk_index in 0..terrain.chunks.len() {
+            if !terrain.chunks[chunk_index].dirty {
+                continue;
+            }
+            let min = terrain.origin + terrain.chunk_pos(chunk_index) * CHUNK_SIZE;
+            let mesh = greedy_mesh(&terrain, min);
+            let material = terrain.material.clone();
+            let chunk = &mut terrain.chunks[chunk_index];
+            chunk.dirty = false;
+            match (mesh, chunk.entity) {
+                (Some(mesh), Some(entity)) => {
+                    commands.entity(entity).insert(meshes.add(mesh));
+                }
+                (Some(mesh), None) => {
+                    let entity = commands
+                        .spawn(PbrBundle {
+                            mesh: meshes.add(mesh),
+                            material,
+                            ..default()
+                        })
+                        .id();
+                    commands.entity(terrain_entity).add_child(entity);
+                    chunk.entity = Some(entity);
+                }
+                //Chunk became empty.
+                (None, Some(entity)) => {
+                    commands.entity(entity).despawn_recursive();
+                    chunk.entity = None;
+                }
+                (None, None) => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    ///Cells 0 to 31 on every axis, which is 2 chunks along each.
+    fn terrain() -> Terrain {
+        Terrain::new(
+            AABB::new(Vec3::splat(-0.5), Vec3::splat(31.5)),
+            Handle::default(),
+        )
+    }
+
+    #[test]
+    fn raycast_across_chunks() {
+        let mut terrain = terrain();
+        //First cell of second chunk, hit from inside of first one.
+        terrain.set(IVec3::new(16, 0, 0), Voxel::Rock);
+        let hit = terrain
+            .raycast(&Ray::new(Vec3::new(10., 0., 0.), Vec3::X))
+            .unwrap();
+        assert_eq!(hit.cell, IVec3::new(16, 0, 0));
+        assert_eq!(hit.normal, IVec3::NEG_X);
+        assert!((hit.t - 5.5).abs() < 1e-4);
+
+        //Last cell of lower chunk, hit from above of volume.
+        terrain.set(IVec3::new(16, 15, 16), Voxel::Rock);
+        let hit = terrain
+            .raycast(&Ray::new(Vec3::new(16., 40., 16.), Vec3::NEG_Y))
+            .unwrap();
+        assert_eq!(hit.cell, IVec3::new(16, 15, 16));
+        assert_eq!(hit.normal, IVec3::Y);
+        assert!((hit.t - 24.5).abs() < 1e-4);
+
+        assert!(terrain
+            .raycast(&Ray::new(Vec3::new(10., 40., 10.), Vec3::NEG_Y))
+            .is_none());
+    }
+
+    #[test]
+    fn ground_of_column() {
+        let mut terrain = terrain();
+        terrain.set(IVec3::new(3, 0, 3), Voxel::Rock);
+        terrain.set(IVec3::new(3, 1, 3), Voxel::Dirt);
+        terrain.set(IVec3::new(3, 5, 3), Voxel::Grass);
+        assert_eq!(terrain.ground(Vec3::new(3., 10., 3.)), 5.5);
+        //Point is rounded to cell, so cell above overhang still sees it.
+        assert_eq!(terrain.ground(Vec3::new(3.4, 4.2, 2.7)), 1.5);
+        assert_eq!(terrain.ground(Vec3::new(4., 10., 3.)), -0.5);
+    }
+
+    #[test]
+    fn set_on_chunk_edge_dirties_neighbor() {
+        let mut terrain = terrain();
+        let chunk = |terrain: &Terrain, cell| terrain.index(cell).unwrap().0;
+        let dirty = |terrain: &Terrain| {
+            (0..terrain.chunks.len())
+                .filter(|&index| terrain.chunks[index].dirty)
+                .collect::<Vec<_>>()
+        };
+        let clean = |terrain: &mut Terrain| {
+            for chunk in terrain.chunks.iter_mut() {
+                chunk.dirty = false;
+            }
+        };
+
+        clean(&mut terrain);
+        terrain.set(IVec3::new(15, 3, 3), Voxel::Rock);
+        assert_eq!(
+            dirty(&terrain),
+            [
+                chunk(&terrain, IVec3::new(15, 3, 3)),
+                chunk(&terrain, IVec3::new(16, 3, 3))
+            ]
+        );
+
+        //Inside of chunk, and setting same voxel again, leave neighbors alone.
+        clean(&mut terrain);
+        terrain.set(IVec3::new(5, 5, 5), Voxel::Rock);
+        terrain.set(IVec3::new(15, 3, 3), Voxel::Rock);
+        assert_eq!(dirty(&terrain), [chunk(&terrain, IVec3::new(5, 5, 5))]);
+    }
+}
@@ -0,0 +1,193 @@
+use crate::asset::{Themes, THEME_DEFAULT};
+
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+
+use serde::Deserialize;
+
+///Size of text on buttons that other text sizes are written against.
+const BASE_TEXT_SIZE: f32 = 30.0;
+
+///Look of ui. Loaded from file, and every ui node follows when file changes.
+/// - Fields missing in file keep default.
+#[derive(Resource, Clone, PartialEq, Debug, Deserialize, TypeUuid)]
+#[uuid = "7c09c352-9b2a-4dc4-82c1-79503f6ba7ea"]
+#[serde(default)]
+pub struct Theme {
+    ///Background of popups.
+    pub background: Color,
+    pub text_bright: Color,
+    pub text_dark: Color,
+    pub button: Color,
+    pub button_hover: Color,
+    pub button_pressed: Color,
+    pub button_disabled: Color,
+    ///Width and height of button in pixels.
+    pub button_size: Vec2,
+    ///Size of text on buttons. Other text keeps its ratio to it.
+    pub text_size: f32,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            background: Color::WHITE,
+            text_bright: Color::YELLOW,
+            text_dark: Color::BLACK,
+            button: Color::BLACK,
+            button_hover: Color::GRAY,
+            button_pressed: Color::DARK_GRAY,
+            button_disabled: Color::rgba(0.0, 0.0, 0.0, 0.4),
+            button_size: Vec2::new(150.0, 65.0),
+            text_size: BASE_TEXT_SIZE,
+        }
+    }
+}
+
+impl Theme {
+    ///Font size of text that is given size with default theme.
+    pub fn font_size(&self, size: f32) -> f32 {
+        size * self.text_size / BASE_TEXT_SIZE
+    }
+}
+
+///Which theme color text has.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum TextColor {
+    ///On buttons and dark backgrounds.
+    Bright,
+    ///On popup background.
+    Dark,
+}
+
+impl TextColor {
+    pub fn of(self, theme: &Theme) -> Color {
+        match self {
+            TextColor::Bright => theme.text_bright,
+            TextColor::Dark => theme.text_dark,
+        }
+    }
+}
+
+///Text that follows theme, with its size for default theme.
+#[derive(Component)]
+pub struct ThemedText {
+    pub size: f32,
+    pub color: TextColor,
+}
+
+///Node that has background color of theme.
+#[derive(Component)]
+pub struct Panel;
+
+///Reads `.theme.ron` files.
+#[derive(Default)]
+struct ThemeLoader;
+
+impl AssetLoader for ThemeLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let theme = ron::de::from_bytes::<Theme>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(theme));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["theme.ron"]
+    }
+}
+
+///Batch setup of theme. Default theme is used until file is loaded.
+pub struct ThemePlugin;
+
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<Theme>()
+            .init_asset_loader::<ThemeLoader>()
+            .init_resource::<Theme>()
+            .add_system_to_stage(CoreStage::PreUpdate, load_theme)
+            .add_system_to_stage(CoreStage::PostUpdate, restyle);
+    }
+}
+
+///Makes theme file current whenever it is loaded or changed.
+fn load_theme(
+    mut events: EventReader<AssetEvent<Theme>>,
+    themes: Res<Themes>,
+    assets: Res<Assets<Theme>>,
+    mut theme: ResMut<Theme>,
+) {
+    let Some(handle) = themes.get(THEME_DEFAULT) else {
+        return;
+    };
+    for event in events.iter() {
+        if let AssetEvent::Created { handle: changed } | AssetEvent::Modified { handle: changed } =
+            event
+        {
+            if changed == handle {
+                if let Some(loaded) = assets.get(handle) {
+                    info!("Theme {THEME_DEFAULT} applied");
+                    *theme = loaded.clone();
+                }
+            }
+        }
+    }
+}
+
+///Updates spawned nodes to changed theme. Buttons are colored by `style_buttons` anyway.
+fn restyle(
+    theme: Res<Theme>,
+    mut last: Local<Theme>,
+    mut texts: Query<(&mut Text, &ThemedText)>,
+    mut panels: Query<&mut BackgroundColor, With<Panel>>,
+    mut buttons: Query<&mut Style, With<Button>>,
+) {
+    if !theme.is_changed() || *theme == *last {
+        return;
+    }
+    for (mut text, themed) in texts.iter_mut() {
+        for section in text.sections.iter_mut() {
+            section.style.font_size = theme.font_size(themed.size);
+            section.style.color = themed.color.of(&theme);
+        }
+    }
+    for mut color in panels.iter_mut() {
+        color.0 = theme.background;
+    }
+    //Buttons of own size, like arrows, keep it.
+    let old = Size::new(Val::Px(last.button_size.x), Val::Px(last.button_size.y));
+    for mut style in buttons.iter_mut() {
+        if style.size == old {
+            style.size = Size::new(Val::Px(theme.button_size.x), Val::Px(theme.button_size.y));
+        }
+    }
+    *last = theme.clone();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn theme_file_parses() {
+        let text = std::fs::read_to_string(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/assets/ui/default.theme.ron"
+        ))
+        .unwrap();
+        assert_eq!(ron::from_str::<Theme>(&text).unwrap(), Theme::default());
+        //Missing fields keep default.
+        let partial: Theme = ron::from_str("(text_size: 15.0)").unwrap();
+        assert_eq!(partial.font_size(40.0), 20.0);
+        assert_eq!(partial.button, Theme::default().button);
+    }
+}
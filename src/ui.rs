@@ -1,24 +1,59 @@
 use crate::{
     asset::{Fonts, FONT_SCHLUBER},
+    controls::InputAction,
     func::Action,
+    layout::{Anchor, Anchored},
+    locale::Localized,
     states::*,
+    theme::{Panel, TextColor, Theme, ThemedText},
 };
 
-use bevy::{app::AppExit, input::Input, prelude::*, window::WindowCloseRequested};
+use bevy::{
+    app::AppExit, ecs::system::SystemState, input::Input, prelude::*, window::WindowCloseRequested,
+};
+
+///Actions that go back from a menu.
+pub const BACK_ACTIONS: [InputAction; 2] = [InputAction::Pause, InputAction::MenuBack];
 
 pub const PLAY_TEXT: &str = "Play";
 pub const EXIT_TEXT: &str = "Exit";
 pub const ARE_YOU_SURE_TEXT: &str = "Are you sure?";
 pub const YES_TEXT: &str = "Yes";
 pub const NO_TEXT: &str = "No";
-
-pub const UI_BACKGROUND_COLOR: BackgroundColor = BackgroundColor(Color::WHITE);
-
-pub const TEXT_COLOR_BRIGHT: Color = Color::YELLOW;
-pub const TEXT_COLOR_DARK: Color = Color::BLACK;
-
-pub const BUTTON_COLOR_NONE: BackgroundColor = BackgroundColor(Color::BLACK);
-pub const BUTTON_COLOR_HOVER: BackgroundColor = BackgroundColor(Color::GRAY);
+pub const PAUSE_TEXT: &str = "Paused";
+pub const RESUME_TEXT: &str = "Resume";
+pub const SETTINGS_TEXT: &str = "Settings";
+pub const MAIN_MENU_TEXT: &str = "Main Menu";
+pub const QUIT_TEXT: &str = "Quit";
+pub const BACK_TEXT: &str = "Back";
+pub const LOADING_TEXT: &str = "Loading";
+pub const FAILED_TEXT: &str = "Failed to load";
+pub const RETRY_TEXT: &str = "Retry";
+pub const CONTINUE_TEXT: &str = "Continue";
+pub const MOUSE_SENSITIVITY_TEXT: &str = "Mouse sensitivity";
+pub const STICK_SENSITIVITY_TEXT: &str = "Stick sensitivity";
+pub const STICK_DEAD_ZONE_TEXT: &str = "Stick dead zone";
+pub const MOVE_SPEED_TEXT: &str = "Move speed";
+pub const LONG_PRESS_TEXT: &str = "Long press delay";
+pub const DISPLAY_MODE_TEXT: &str = "Window mode";
+pub const VSYNC_TEXT: &str = "VSync";
+pub const ON_TEXT: &str = "On";
+pub const OFF_TEXT: &str = "Off";
+pub const CONTROLS_TEXT: &str = "Controls";
+pub const RESET_TEXT: &str = "Reset";
+pub const PRESS_ANY_TEXT: &str = "Press any input";
+pub const LISTEN_HINT_TEXT: &str = "Escape cancels, Delete clears";
+pub const BOUND_TWICE_TEXT: &str = "Bound twice";
+pub const LANGUAGE_TEXT: &str = "Language";
+pub const UI_SCALE_TEXT: &str = "UI scale";
+pub const MAP_TEXT: &str = "Map";
+pub const FLAT_TEXT: &str = "Flat";
+pub const MONEY_TEXT: &str = "Money";
+pub const WAVE_TEXT: &str = "Wave";
+pub const NEXT_WAVE_TEXT: &str = "Next wave";
+pub const TOWERS_TEXT: &str = "Towers";
+pub const COST_TEXT: &str = "Cost";
+pub const WAVE_STARTED_TEXT: &str = "Wave started";
 
 ///Mark hierarchy info of ui
 #[derive(Component)]
@@ -28,91 +63,228 @@ pub struct HierarchyMark<const N: u32>;
 #[derive(Component)]
 pub struct AppExitMark;
 
-///Go to exit state when requested.
-pub fn close_requested(
-    closed: EventReader<WindowCloseRequested>,
-    mut state: ResMut<GlobalState>,
-    input: Res<Input<KeyCode>>,
-) {
-    if !closed.is_empty() || input.just_pressed(KeyCode::Escape) {
-        state.push_exit()
-    }
-}
+///Button that can't be clicked for now, and looks so.
+#[derive(Component)]
+pub struct Disabled;
 
-///Force app exit via close request on exit state.
-pub fn exit_close_requested(
-    closed: EventReader<WindowCloseRequested>,
-    mut event: EventWriter<AppExit>,
-) {
-    if !closed.is_empty() {
-        event.send(AppExit)
+///Button that keys or gamepad act on, shown as hovered.
+#[derive(Resource, Default)]
+pub struct Focus(pub Option<Entity>);
+
+///Label of navigate systems.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, SystemLabel)]
+pub struct NavigationSystem;
+
+type ClickSystemState<'w, 's> = SystemState<(
+    Res<'w, GlobalState>,
+    Query<
+        'w,
+        's,
+        (Entity, &'w Interaction),
+        (
+            Changed<Interaction>,
+            With<Button>,
+            With<Action>,
+            Without<Disabled>,
+        ),
+    >,
+    Query<'w, 's, &'w Parent>,
+    Query<'w, 's, &'w StateMark>,
+)>;
+
+///Batch setup of buttons that have an `Action`.
+/// - Click runs action, only on buttons of current screen.
+/// - Every button is styled by interaction, focus and `Disabled` the same way.
+/// - Each screen adds `navigate` for its buttons to PostUpdate, so click from keys or gamepad
+///   lands on next Update, after input of this frame was handled by screen.
+pub struct ButtonPlugin;
+
+impl Plugin for ButtonPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Focus>()
+            //After screens handled input, and before their commands could despawn buttons.
+            .add_system_to_stage(CoreStage::Update, click_buttons.before_commands())
+            .add_system_to_stage(CoreStage::PostUpdate, style_buttons.after(NavigationSystem));
     }
 }
 
-///Close exit state via esc.
-pub fn exit_esc(mut state: ResMut<GlobalState>, input: Res<Input<KeyCode>>) {
-    if input.just_pressed(KeyCode::Escape) {
-        state.pop_exit();
+///Runs actions of clicked buttons.
+fn click_buttons(world: &mut World, system_state: &mut ClickSystemState) {
+    let (state, clicked, parents, marks) = system_state.get(world);
+    let current = state.current();
+    //Screen of button is the one its node or ancestor is marked with.
+    let screen = |entity| {
+        std::iter::successors(Some(entity), |entity| {
+            parents.get(*entity).ok().map(Parent::get)
+        })
+        .find_map(|entity| marks.get(entity).ok())
+        .map(StateMark::state)
+    };
+    let entities = clicked
+        .iter()
+        .filter(|(entity, interaction)| {
+            **interaction == Interaction::Clicked && screen(*entity) == Some(current)
+        })
+        .map(|(entity, _)| entity)
+        .collect::<Vec<_>>();
+    for entity in entities {
+        Action::run(world, entity);
     }
 }
 
-///Interaction with no button of exit popup.
-pub fn exit_no_button(
-    mut interaction_query: Query<
+///Colors buttons by what is going on with them.
+fn style_buttons(
+    theme: Res<Theme>,
+    focus: Res<Focus>,
+    mut buttons: Query<
         (
+            Entity,
             &Interaction,
+            Option<&Disabled>,
             &mut BackgroundColor,
-            &Action<fn(&mut GlobalState)>,
-            &AppExitMark,
         ),
-        (Changed<Interaction>, With<Button>),
+        (With<Button>, With<Action>),
     >,
-    mut state: ResMut<GlobalState>,
 ) {
-    for (interaction, mut color, func, _) in interaction_query.iter_mut() {
-        match *interaction {
-            Interaction::Clicked => func.run(&mut *state),
-            Interaction::Hovered => {
-                *color = BUTTON_COLOR_HOVER;
-            }
-            Interaction::None => {
-                *color = BUTTON_COLOR_NONE;
-            }
+    for (entity, interaction, disabled, mut color) in buttons.iter_mut() {
+        let style = match (disabled, interaction) {
+            (Some(_), _) => theme.button_disabled,
+            (None, Interaction::Clicked) => theme.button_pressed,
+            (None, Interaction::Hovered) => theme.button_hover,
+            (None, Interaction::None) if focus.0 == Some(entity) => theme.button_hover,
+            (None, Interaction::None) => theme.button,
+        };
+        //Keep change detection quiet.
+        if color.0 != style {
+            color.0 = style;
         }
     }
 }
 
-///Interaction with yes button of exit popup.
-pub fn exit_yes_button(
-    mut interaction_query: Query<
+///Moves focus between buttons with given mark and clicks focused one, for keys and gamepad.
+/// - Nothing is focused until first move, and moving mouse drops focus.
+/// - Buttons are in reading order, so previous and next also go along rows.
+/// - Disabled buttons are skipped.
+pub fn navigate<M: Component>(
+    actions: Res<Input<InputAction>>,
+    mut focus: ResMut<Focus>,
+    mut clicked: Local<Option<Entity>>,
+    mut cursor: EventReader<CursorMoved>,
+    mut buttons: Query<
         (
-            &Interaction,
-            &mut BackgroundColor,
-            &Action<fn(&mut EventWriter<AppExit>)>,
-            &AppExitMark,
+            Entity,
+            &GlobalTransform,
+            &mut Interaction,
+            Option<&Disabled>,
         ),
-        (Changed<Interaction>, With<Button>),
+        (With<Button>, With<M>),
     >,
-    mut event: EventWriter<AppExit>,
 ) {
-    for (interaction, mut color, func, _) in interaction_query.iter_mut() {
-        match *interaction {
-            Interaction::Clicked => func.run(&mut event),
-            Interaction::Hovered => {
-                *color = BUTTON_COLOR_HOVER;
-            }
-            Interaction::None => {
-                *color = BUTTON_COLOR_NONE;
+    //Release click of last frame, which screen has seen by now.
+    if let Some(entity) = clicked.take() {
+        if let Ok((_, _, mut interaction, _)) = buttons.get_mut(entity) {
+            if *interaction == Interaction::Clicked {
+                *interaction = Interaction::None;
             }
         }
     }
+    if cursor.iter().count() > 0 {
+        focus.0 = None;
+    }
+
+    let mut order = buttons
+        .iter()
+        .filter(|(_, _, _, disabled)| disabled.is_none())
+        .map(|(entity, transform, _, _)| (entity, transform.translation()))
+        .collect::<Vec<_>>();
+    //Ui y goes up.
+    order.sort_by(|(_, a), (_, b)| b.y.total_cmp(&a.y).then(a.x.total_cmp(&b.x)));
+    let current = order
+        .iter()
+        .position(|(entity, _)| Some(*entity) == focus.0);
+
+    let step = actions.just_pressed(InputAction::MenuNext) as isize
+        - actions.just_pressed(InputAction::MenuPrevious) as isize;
+    if step != 0 && !order.is_empty() {
+        let index = match current {
+            Some(index) => (index as isize + step).rem_euclid(order.len() as isize) as usize,
+            None if step > 0 => 0,
+            None => order.len() - 1,
+        };
+        focus.0 = Some(order[index].0);
+    } else if let (Some(index), true) = (current, actions.just_pressed(InputAction::MenuConfirm)) {
+        let entity = order[index].0;
+        if let Ok((_, _, mut interaction, _)) = buttons.get_mut(entity) {
+            *interaction = Interaction::Clicked;
+            *clicked = Some(entity);
+        }
+    }
+}
+
+///Go to exit state when requested.
+pub fn close_requested(
+    closed: EventReader<WindowCloseRequested>,
+    mut state: ResMut<GlobalState>,
+    actions: Res<Input<InputAction>>,
+) {
+    if !closed.is_empty() || actions.any_just_pressed(BACK_ACTIONS) {
+        let _ = state.push_exit();
+    }
+}
+
+///Go back to previous screen via esc, or to exit state when window is about to close.
+pub fn pop_requested(
+    closed: EventReader<WindowCloseRequested>,
+    mut state: ResMut<GlobalState>,
+    actions: Res<Input<InputAction>>,
+) {
+    if !closed.is_empty() {
+        let _ = state.push_exit();
+    } else if actions.any_just_pressed(BACK_ACTIONS) {
+        let _ = state.back();
+    }
+}
+
+///Force app exit via close request on exit state.
+pub fn exit_close_requested(
+    closed: EventReader<WindowCloseRequested>,
+    mut event: EventWriter<AppExit>,
+) {
+    if !closed.is_empty() {
+        event.send(AppExit)
+    }
+}
+
+///Close exit state via esc.
+pub fn exit_esc(mut state: ResMut<GlobalState>, actions: Res<Input<InputAction>>) {
+    if actions.any_just_pressed(BACK_ACTIONS) {
+        let _ = state.back();
+    }
+}
+
+///Shortcut to create popup node in the middle of screen.
+/// - Size is percent of window, but no smaller than its content needs.
+pub fn create_popup(theme: &Theme, height: f32) -> (NodeBundle, Panel, Anchored) {
+    let node = NodeBundle {
+        style: Style {
+            size: Size::new(Val::Percent(30.0), Val::Percent(height)),
+            min_size: Size::new(Val::Px(420.0), Val::Px(height * 6.0)),
+            flex_direction: FlexDirection::ColumnReverse,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        background_color: theme.background.into(),
+        ..default()
+    };
+    (node, Panel, Anchored::new(Anchor::Center, Vec2::ZERO))
 }
 
 ///Shortcut to create button.
-pub fn create_button() -> ButtonBundle {
+pub fn create_button(theme: &Theme) -> ButtonBundle {
     ButtonBundle {
         style: Style {
-            size: Size::new(Val::Px(150.0), Val::Px(65.0)),
+            size: Size::new(Val::Px(theme.button_size.x), Val::Px(theme.button_size.y)),
             // center button
             margin: UiRect::all(Val::Auto),
             // horizontally center child text
@@ -121,62 +293,80 @@ pub fn create_button() -> ButtonBundle {
             align_items: AlignItems::Center,
             ..default()
         },
-        background_color: BUTTON_COLOR_NONE,
+        background_color: theme.button.into(),
         ..default()
     }
 }
 
-///Shortcut to create text.
+///Shortcut to create text, of given size with default theme.
+/// - Text is English, and shows translation of current language.
 pub fn create_text(
+    text: &'static str,
+    fonts: &Res<Fonts>,
+    theme: &Theme,
+    size: f32,
+    color: TextColor,
+) -> (TextBundle, ThemedText, Localized) {
+    let (bundle, themed) = create_value_text(text, fonts, theme, size, color);
+    (bundle, themed, Localized(text))
+}
+
+///Shortcut to create text that system keeps up to date, so it is shown as given.
+pub fn create_value_text(
     text: impl Into<String>,
     fonts: &Res<Fonts>,
+    theme: &Theme,
     size: f32,
-    color: Color,
-) -> TextBundle {
-    TextBundle::from_section(
+    color: TextColor,
+) -> (TextBundle, ThemedText) {
+    let font_size = theme.font_size(size);
+    let bundle = TextBundle::from_section(
         text,
         TextStyle {
             font: fonts[FONT_SCHLUBER].clone(),
-            font_size: size,
-            color,
+            font_size,
+            color: color.of(theme),
         },
     )
     .with_style(Style {
         //center button
         margin: UiRect {
-            top: Val::Px(size * 0.25),
+            top: Val::Px(font_size * 0.25),
             ..default()
         },
         ..default()
     })
-    .with_text_alignment(TextAlignment::CENTER)
+    .with_text_alignment(TextAlignment::CENTER);
+    (bundle, ThemedText { size, color })
 }
 
 ///Setup exit popup.
-pub fn setup_exit(mut commands: Commands, state: Res<GlobalState>, fonts: Res<Fonts>) {
+pub fn setup_exit(
+    mut commands: Commands,
+    state: Res<GlobalState>,
+    fonts: Res<Fonts>,
+    theme: Res<Theme>,
+) {
     //Node that represent popup.
     commands
         .spawn((
             NodeBundle {
                 style: Style {
                     size: Size::new(Val::Percent(40.0), Val::Percent(24.0)),
-                    position_type: PositionType::Absolute,
-                    position: UiRect::new(
-                        Val::Percent(30.0),
-                        Val::Percent(30.0),
-                        Val::Percent(38.0),
-                        Val::Percent(38.0),
-                    ),
+                    min_size: Size::new(Val::Px(360.0), Val::Px(170.0)),
                     flex_wrap: FlexWrap::Wrap,
                     justify_content: JustifyContent::Center,
                     align_items: AlignItems::Center,
                     align_content: AlignContent::SpaceAround,
                     ..default()
                 },
-                background_color: UI_BACKGROUND_COLOR,
+                background_color: theme.background.into(),
                 ..default()
             },
-            state.mark(),
+            Panel,
+            Anchored::new(Anchor::Center, Vec2::ZERO),
+            //Exit state is only ever left by pop.
+            state.mark().until_pop(),
         ))
         .with_children(|parent| {
             //Container for text.
@@ -195,33 +385,44 @@ pub fn setup_exit(mut commands: Commands, state: Res<GlobalState>, fonts: Res<Fo
                     parent.spawn(create_text(
                         ARE_YOU_SURE_TEXT,
                         &fonts,
+                        &theme,
                         30.0,
-                        TEXT_COLOR_DARK,
+                        TextColor::Dark,
                     ));
                 });
             //yes button
             parent
                 .spawn((
-                    create_button(),
-                    Action::<for<'a> fn(&'a mut EventWriter<AppExit>)>::new(
-                        |e: &mut EventWriter<AppExit>| e.send(AppExit),
-                    ),
+                    create_button(&theme),
+                    Action::new(|mut exit: EventWriter<AppExit>| exit.send(AppExit)),
                     AppExitMark,
                 ))
                 .with_children(|parent| {
-                    parent.spawn(create_text(YES_TEXT, &fonts, 30.0, TEXT_COLOR_BRIGHT));
+                    parent.spawn(create_text(
+                        YES_TEXT,
+                        &fonts,
+                        &theme,
+                        30.0,
+                        TextColor::Bright,
+                    ));
                 });
             //no button
             parent
                 .spawn((
-                    create_button(),
-                    Action::<for<'a> fn(&'a mut GlobalState)>::new(|g: &mut GlobalState| {
-                        g.pop_exit()
+                    create_button(&theme),
+                    Action::new(|mut g: ResMut<GlobalState>| {
+                        let _ = g.pop_exit();
                     }),
                     AppExitMark,
                 ))
                 .with_children(|parent| {
-                    parent.spawn(create_text(NO_TEXT, &fonts, 30.0, TEXT_COLOR_BRIGHT));
+                    parent.spawn(create_text(
+                        NO_TEXT,
+                        &fonts,
+                        &theme,
+                        30.0,
+                        TextColor::Bright,
+                    ));
                 });
         });
 }